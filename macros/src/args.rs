@@ -0,0 +1,459 @@
+use syn::{
+    parenthesized,
+    parse::{self, Parse, ParseStream},
+    punctuated::Punctuated,
+    token, Ident, LitInt, LitStr, Path, Token, Type,
+};
+
+#[derive(Clone, Default)]
+pub struct Args {
+    pub address: Option<LitInt>,
+    pub align: Option<LitInt>,
+    pub core: Option<Path>,
+    pub count: Option<LitInt>,
+    pub deny_duplicates: bool,
+    pub derives: Option<Punctuated<Path, Token![,]>>,
+    pub export_name: Option<LitStr>,
+    pub ffi: Option<Punctuated<Field, Token![,]>>,
+    // traits that the proxy implements by forwarding to the variable
+    pub forward: Vec<Ident>,
+    pub init: bool,
+    pub lazy: Option<Path>,
+    pub max_size: Option<LitInt>,
+    pub name: Option<Ident>,
+    pub no_export: bool,
+    pub no_mangle: bool,
+    // `Some(crc)` if the `noinit` argument is used
+    pub noinit: Option<bool>,
+    pub on_drop: Option<Path>,
+    // fill pattern of `stack(paint = ..)`
+    pub paint: Option<LitInt>,
+    pub per_cpu: Option<LitInt>,
+    pub pinned: bool,
+    pub readable: bool,
+    pub refcount: bool,
+    pub release: bool,
+    pub reset: bool,
+    pub section: Option<LitStr>,
+    pub send: Option<AutoImpl>,
+    pub split: Option<Punctuated<Field, Token![,]>>,
+    pub stack: bool,
+    pub sync: Option<AutoImpl>,
+    pub typestate: bool,
+    pub uninit: bool,
+    pub volatile: bool,
+    pub zeroize: bool,
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        let mut args = Args::default();
+
+        while !input.is_empty() {
+            let ident: Ident = input.parse()?;
+
+            match &*ident.to_string() {
+                "Send" => {
+                    if args.send.is_some() {
+                        return Err(parse::Error::new(ident.span(), "this trait appears twice"));
+                    }
+
+                    args.send = Some(input.parse()?);
+                }
+                "Sync" => {
+                    if args.sync.is_some() {
+                        return Err(parse::Error::new(ident.span(), "this trait appears twice"));
+                    }
+
+                    args.sync = Some(input.parse()?);
+                }
+                "Debug" | "Display" | "Eq" | "Hash" | "Ord" | "PartialEq" | "PartialOrd" => {
+                    if args.forward.contains(&ident) {
+                        return Err(parse::Error::new(ident.span(), "this trait appears twice"));
+                    }
+
+                    args.forward.push(ident);
+                }
+                "address" => {
+                    unique(&ident, args.address.is_none())?;
+
+                    let lit: LitInt = value(input)?;
+                    if lit.value() == 0 {
+                        return Err(parse::Error::new(lit.span(), "the address can't be null"));
+                    }
+
+                    args.address = Some(lit);
+                }
+                "align" => {
+                    unique(&ident, args.align.is_none())?;
+
+                    let lit: LitInt = value(input)?;
+                    if !lit.value().is_power_of_two() {
+                        return Err(parse::Error::new(
+                            lit.span(),
+                            "the alignment must be a power of two",
+                        ));
+                    }
+
+                    args.align = Some(lit);
+                }
+                "core" => {
+                    unique(&ident, args.core.is_none())?;
+
+                    args.core = Some(value(input)?);
+                }
+                "count" => {
+                    unique(&ident, args.count.is_none())?;
+
+                    let lit: LitInt = value(input)?;
+                    if lit.value() == 0 {
+                        return Err(parse::Error::new(
+                            lit.span(),
+                            "the count must be at least 1",
+                        ));
+                    }
+
+                    args.count = Some(lit);
+                }
+                "deny_duplicates" => {
+                    unique(&ident, !args.deny_duplicates)?;
+
+                    if args.no_export {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`deny_duplicates` can't be used together with `no_export`",
+                        ));
+                    }
+
+                    args.deny_duplicates = true;
+                }
+                "derive" => {
+                    unique(&ident, args.derives.is_none())?;
+
+                    let content;
+                    parenthesized!(content in input);
+                    let derives = Punctuated::<Path, Token![,]>::parse_terminated(&content)?;
+
+                    for path in &derives {
+                        let last = &path.segments.iter().last().unwrap().ident;
+                        if last == "Clone" || last == "Copy" || last == "Default" {
+                            return Err(parse::Error::new(
+                                last.span(),
+                                "this derive would let safe code create more than one proxy",
+                            ));
+                        }
+                    }
+
+                    args.derives = Some(derives);
+                }
+                "export_name" => {
+                    unique(&ident, args.export_name.is_none())?;
+
+                    if args.no_mangle {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`export_name` can't be used together with `no_mangle`",
+                        ));
+                    }
+
+                    args.export_name = Some(value(input)?);
+                }
+                "ffi" => {
+                    unique(&ident, args.ffi.is_none())?;
+
+                    // optional list of fields that get C getters and setters
+                    args.ffi = Some(if input.peek(token::Paren) {
+                        let content;
+                        parenthesized!(content in input);
+                        Punctuated::parse_terminated(&content)?
+                    } else {
+                        Punctuated::new()
+                    });
+                }
+                "init" => {
+                    unique(&ident, !args.init)?;
+
+                    args.init = true;
+                }
+                "lazy" => {
+                    unique(&ident, args.lazy.is_none())?;
+
+                    args.lazy = Some(value(input)?);
+                }
+                "max_size" => {
+                    unique(&ident, args.max_size.is_none())?;
+
+                    args.max_size = Some(value(input)?);
+                }
+                "name" => {
+                    unique(&ident, args.name.is_none())?;
+
+                    let lit: LitStr = value(input)?;
+                    args.name = Some(lit.parse()?);
+                }
+                "no_export" => {
+                    unique(&ident, !args.no_export)?;
+
+                    if args.readable {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`no_export` can't be used together with `readable`",
+                        ));
+                    }
+
+                    if args.deny_duplicates {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`no_export` can't be used together with `deny_duplicates`",
+                        ));
+                    }
+
+                    args.no_export = true;
+                }
+                "no_mangle" => {
+                    unique(&ident, !args.no_mangle)?;
+
+                    if args.export_name.is_some() {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`no_mangle` can't be used together with `export_name`",
+                        ));
+                    }
+
+                    args.no_mangle = true;
+                }
+                "noinit" => {
+                    unique(&ident, args.noinit.is_none())?;
+
+                    // optional `(crc)`
+                    let crc = if input.peek(token::Paren) {
+                        let content;
+                        parenthesized!(content in input);
+                        let ident: Ident = content.parse()?;
+                        if ident != "crc" || !content.is_empty() {
+                            return Err(parse::Error::new(ident.span(), "expected `crc`"));
+                        }
+                        true
+                    } else {
+                        false
+                    };
+
+                    args.noinit = Some(crc);
+                }
+                "on_drop" => {
+                    unique(&ident, args.on_drop.is_none())?;
+
+                    args.on_drop = Some(value(input)?);
+                }
+                "per_cpu" => {
+                    unique(&ident, args.per_cpu.is_none())?;
+
+                    let lit: LitInt = value(input)?;
+                    if lit.value() == 0 {
+                        return Err(parse::Error::new(
+                            lit.span(),
+                            "the number of cores must be at least 1",
+                        ));
+                    }
+
+                    args.per_cpu = Some(lit);
+                }
+                "pinned" => {
+                    unique(&ident, !args.pinned)?;
+
+                    args.pinned = true;
+                }
+                "readable" => {
+                    unique(&ident, !args.readable)?;
+
+                    if args.no_export {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`readable` can't be used together with `no_export`",
+                        ));
+                    }
+
+                    args.readable = true;
+                }
+                "refcount" => {
+                    unique(&ident, !args.refcount)?;
+
+                    args.refcount = true;
+                }
+                "release" => {
+                    unique(&ident, !args.release)?;
+
+                    if !cfg!(feature = "take") {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`release` requires the `take` feature",
+                        ));
+                    }
+
+                    args.release = true;
+                }
+                "reset" => {
+                    unique(&ident, !args.reset)?;
+
+                    args.reset = true;
+                }
+                "section" => {
+                    unique(&ident, args.section.is_none())?;
+
+                    if args.uninit {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`section` can't be used together with `uninit`",
+                        ));
+                    }
+
+                    args.section = Some(value(input)?);
+                }
+                "split" => {
+                    unique(&ident, args.split.is_none())?;
+
+                    let content;
+                    parenthesized!(content in input);
+                    let fields = Punctuated::parse_terminated(&content)?;
+                    if fields.is_empty() {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`split` expects a list of `field: Type` pairs",
+                        ));
+                    }
+
+                    args.split = Some(fields);
+                }
+                "stack" => {
+                    unique(&ident, !args.stack)?;
+
+                    // optional `(paint = 0xa5)`
+                    if input.peek(token::Paren) {
+                        let content;
+                        parenthesized!(content in input);
+                        let key: Ident = content.parse()?;
+                        if key != "paint" {
+                            return Err(parse::Error::new(key.span(), "expected `paint`"));
+                        }
+
+                        let lit: LitInt = value(&content)?;
+                        if lit.value() > 0xff {
+                            return Err(parse::Error::new(
+                                lit.span(),
+                                "the fill pattern must fit in a byte",
+                            ));
+                        }
+
+                        if !content.is_empty() {
+                            return Err(content.error("unexpected token"));
+                        }
+
+                        args.paint = Some(lit);
+                    }
+
+                    args.stack = true;
+                }
+                "uninit" => {
+                    unique(&ident, !args.uninit)?;
+
+                    if args.section.is_some() {
+                        return Err(parse::Error::new(
+                            ident.span(),
+                            "`uninit` can't be used together with `section`",
+                        ));
+                    }
+
+                    args.uninit = true;
+                }
+                "typestate" => {
+                    unique(&ident, !args.typestate)?;
+
+                    args.typestate = true;
+                }
+                "volatile" => {
+                    unique(&ident, !args.volatile)?;
+
+                    args.volatile = true;
+                }
+                "zeroize" => {
+                    unique(&ident, !args.zeroize)?;
+
+                    args.zeroize = true;
+                }
+                _ => return Err(parse::Error::new(ident.span(), "unknown argument")),
+            }
+
+            if input.is_empty() {
+                break;
+            }
+
+            input.parse::<Token![,]>()?;
+        }
+
+        Ok(args)
+    }
+}
+
+/// How an auto trait (`Send` or `Sync`) is implemented for the proxy
+#[derive(Clone, Copy, PartialEq)]
+pub enum AutoImpl {
+    /// Only if the type of the `static` variable implements the trait (e.g. `Send`)
+    Bounded,
+    /// Unconditionally (e.g. `Send(force)`)
+    Forced,
+}
+
+impl Parse for AutoImpl {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        if !input.peek(token::Paren) {
+            return Ok(AutoImpl::Bounded);
+        }
+
+        let content;
+        parenthesized!(content in input);
+        let ident: Ident = content.parse()?;
+        if ident != "force" || !content.is_empty() {
+            return Err(parse::Error::new(ident.span(), "expected `force`"));
+        }
+
+        Ok(AutoImpl::Forced)
+    }
+}
+
+/// A `field: Type` pair of the `split` and `ffi` arguments
+#[derive(Clone)]
+pub struct Field {
+    pub ident: Ident,
+    pub ty: Type,
+}
+
+impl Parse for Field {
+    fn parse(input: ParseStream) -> parse::Result<Self> {
+        let ident = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let ty = input.parse()?;
+
+        Ok(Field { ident, ty })
+    }
+}
+
+/// Errors if an argument that's already been set (`!is_unset`) appears again
+fn unique(ident: &Ident, is_unset: bool) -> parse::Result<()> {
+    if is_unset {
+        Ok(())
+    } else {
+        Err(parse::Error::new(
+            ident.span(),
+            "this argument appears twice",
+        ))
+    }
+}
+
+/// Parses the `= value` part of a `key = value` argument
+fn value<T>(input: ParseStream) -> parse::Result<T>
+where
+    T: Parse,
+{
+    input.parse::<Token![=]>()?;
+    input.parse()
+}
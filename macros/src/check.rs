@@ -0,0 +1,628 @@
+use proc_macro2::{Span, TokenStream as TokenStream2, TokenTree};
+use quote::ToTokens;
+use syn::{parse, Ident, Type};
+
+use args::Args;
+use item::Static;
+use util::{array, maybe_uninit};
+
+/// Checks the arguments of a variable declared in a `#[Singletons]` module
+pub fn check_member(item: &Static, args: &Args) -> parse::Result<()> {
+    // `Resources` creates every proxy with `Singleton::new` or `take`
+    let arg = if args.address.is_some() {
+        Some("address")
+    } else if args.init {
+        Some("init")
+    } else if args.lazy.is_some() {
+        Some("lazy")
+    } else if args.noinit.is_some() {
+        Some("noinit")
+    } else if args.typestate {
+        Some("typestate")
+    } else if args.volatile {
+        Some("volatile")
+    } else {
+        None
+    };
+
+    if let Some(arg) = arg {
+        return Err(parse::Error::new(
+            item.ident.span(),
+            format!("`{}` can't be used inside a `#[Singletons]` module", arg),
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn check(item: &Static, args: &Args) -> parse::Result<()> {
+    for attr in &item.attrs {
+        if attr.path.is_ident("no_mangle") || attr.path.is_ident("export_name") {
+            return Err(parse::Error::new_spanned(
+                attr,
+                "the symbol of the `static` variable is picked by the `Singleton` attribute; \
+                 use the `export_name` or `no_mangle` arguments to change it",
+            ));
+        }
+
+        if attr.path.is_ident("thread_local") {
+            return Err(parse::Error::new_spanned(
+                attr,
+                "thread local variables can't be owned singletons",
+            ));
+        }
+
+        if attr.path.is_ident("link_section") && (args.section.is_some() || args.uninit) {
+            return Err(parse::Error::new_spanned(
+                attr,
+                "`#[link_section]` can't be used together with the `section` or `uninit` arguments",
+            ));
+        }
+    }
+
+    if let Some(span) = non_static_lifetime(item.ty.clone().into_token_stream(), &mut vec![]) {
+        return Err(parse::Error::new(
+            span,
+            "the type of an owned singleton can't contain non-`'static` lifetimes",
+        ));
+    }
+
+    if let Some(address) = args.address.as_ref() {
+        if let Some(expr) = item.expr.as_ref() {
+            return Err(parse::Error::new_spanned(
+                expr,
+                "variables placed at a fixed `address` can't have an initializer",
+            ));
+        }
+
+        // there's no `static` variable to speak of
+        let arg = if args.align.is_some() {
+            Some("align")
+        } else if args.count.is_some() {
+            Some("count")
+        } else if args.deny_duplicates {
+            Some("deny_duplicates")
+        } else if args.export_name.is_some() {
+            Some("export_name")
+        } else if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.no_mangle {
+            Some("no_mangle")
+        } else if args.per_cpu.is_some() {
+            Some("per_cpu")
+        } else if args.readable {
+            Some("readable")
+        } else if args.section.is_some() {
+            Some("section")
+        } else if args.uninit {
+            Some("uninit")
+        } else if item.abi.is_some() {
+            Some("extern")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                address.span(),
+                format!("`address` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if args.export_name.is_some() || args.no_mangle {
+        // the symbol is exactly the one given by the user
+        let arg = if args.count.is_some() {
+            Some("count")
+        } else if args.deny_duplicates {
+            Some("deny_duplicates")
+        } else if args.no_export {
+            Some("no_export")
+        } else if args.readable {
+            Some("readable")
+        } else if item.abi.is_some() {
+            Some("extern")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            let (span, name) = match args.export_name.as_ref() {
+                Some(export_name) => (export_name.span(), "export_name"),
+                None => (item.ident.span(), "no_mangle"),
+            };
+
+            return Err(parse::Error::new(
+                span,
+                format!("`{}` can't be used together with `{}`", name, arg),
+            ));
+        }
+    }
+
+    if item.abi.is_some() {
+        let arg = if args.align.is_some() {
+            Some("align")
+        } else if args.deny_duplicates {
+            Some("deny_duplicates")
+        } else if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.section.is_some() {
+            Some("section")
+        } else if args.uninit {
+            Some("uninit")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!(
+                    "`{}` can't be used on variables declared in an `extern` block",
+                    arg
+                ),
+            ));
+        }
+    }
+
+    if let Some(lazy) = args.lazy.as_ref() {
+        if let Some(expr) = item.expr.as_ref() {
+            return Err(parse::Error::new_spanned(
+                expr,
+                "`lazy` variables are initialized by the `lazy` function; remove the initializer",
+            ));
+        }
+
+        let arg = if args.sync.is_some() {
+            // `Deref` may run the initializer so `&Proxy` can't be shared
+            Some("Sync")
+        } else if args.split.is_some() {
+            Some("split")
+        } else if args.uninit {
+            Some("uninit")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new_spanned(
+                lazy,
+                format!("`lazy` can't be used together with `{}`", arg),
+            ));
+        }
+    } else if item.expr.is_none()
+        && item.abi.is_none()
+        && args.address.is_none()
+        && args.noinit.is_none()
+        && !args.init
+    {
+        return Err(parse::Error::new(
+            item.ident.span(),
+            "`static` variables without an initializer require the `lazy`, `init`, `address` or \
+             `noinit` argument",
+        ));
+    }
+
+    if args.init {
+        if item.mutability.is_none() {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                "`init` can only be used on `static mut` variables",
+            ));
+        }
+
+        if let Some(expr) = item.expr.as_ref() {
+            return Err(parse::Error::new_spanned(
+                expr,
+                "`init` variables are initialized at runtime by `init`; remove the initializer",
+            ));
+        }
+
+        // NOTE `init` is the checked constructor so there's no `take` flag to release
+        let arg = if args.address.is_some() {
+            Some("address")
+        } else if item.abi.is_some() {
+            Some("extern")
+        } else if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.noinit.is_some() {
+            Some("noinit")
+        } else if args.per_cpu.is_some() {
+            Some("per_cpu")
+        } else if args.release {
+            Some("release")
+        } else if args.split.is_some() {
+            Some("split")
+        } else if args.stack {
+            Some("stack")
+        } else if args.typestate {
+            Some("typestate")
+        } else if args.uninit {
+            Some("uninit")
+        } else if args.volatile {
+            Some("volatile")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`init` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    match (args.per_cpu.as_ref(), args.core.as_ref()) {
+        (Some(n), None) => {
+            return Err(parse::Error::new(
+                n.span(),
+                "`per_cpu` requires a `core` function that returns the index of the current core",
+            ))
+        }
+        (None, Some(core)) => {
+            return Err(parse::Error::new_spanned(
+                core,
+                "`core` can only be used together with `per_cpu`",
+            ))
+        }
+        (Some(n), Some(_)) => {
+            // the proxy must stay on the core that created it
+            let arg = if args.send.is_some() {
+                Some("Send")
+            } else if args.sync.is_some() {
+                Some("Sync")
+            } else if args.lazy.is_some() {
+                Some("lazy")
+            } else if item.abi.is_some() {
+                Some("extern")
+            } else {
+                None
+            };
+
+            if let Some(arg) = arg {
+                return Err(parse::Error::new(
+                    n.span(),
+                    format!("`per_cpu` can't be used together with `{}`", arg),
+                ));
+            }
+        }
+        (None, None) => {}
+    }
+
+    if args.pinned {
+        // these hand out, or write through, `&mut` references to the pinned variable
+        let arg = if args.on_drop.is_some() {
+            Some("on_drop")
+        } else if args.split.is_some() {
+            Some("split")
+        } else if args.uninit {
+            Some("uninit")
+        } else if args.volatile {
+            Some("volatile")
+        } else if args.zeroize {
+            Some("zeroize")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`pinned` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if let Some(derives) = args.derives.as_ref() {
+        for path in derives {
+            let last = &path.segments.iter().last().unwrap().ident;
+            if args.forward.contains(last) {
+                return Err(parse::Error::new(
+                    last.span(),
+                    format!("`derive({0})` can't be used together with `{0}`", last),
+                ));
+            }
+        }
+    }
+
+    if args.volatile {
+        // these rely on `Deref` / `DerefMut`, which volatile proxies don't implement
+        if let Some(forward) = args.forward.first() {
+            return Err(parse::Error::new(
+                forward.span(),
+                format!("`volatile` can't be used together with `{}`", forward),
+            ));
+        }
+
+        let arg = if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.on_drop.is_some() {
+            Some("on_drop")
+        } else if args.refcount {
+            Some("refcount")
+        } else if args.split.is_some() {
+            Some("split")
+        } else if args.uninit {
+            Some("uninit")
+        } else if args.zeroize {
+            Some("zeroize")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`volatile` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if item.mutability.is_none() {
+        let arg = if args.on_drop.is_some() {
+            Some("on_drop")
+        } else if args.pinned {
+            Some("pinned")
+        } else if args.zeroize {
+            Some("zeroize")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                format!("`{}` can only be used on `static mut` variables", arg),
+            ));
+        }
+    }
+
+    if args.stack {
+        if item.mutability.is_none() {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                "`stack` can only be used on `static mut` variables",
+            ));
+        }
+
+        // painting must leave the elements with valid values
+        let elem = array(&item.ty).and_then(|elem| match elem {
+            Type::Path(ty) if ty.qself.is_none() => ty.path.segments.iter().last(),
+            _ => None,
+        });
+        let is_plain = elem.map_or(false, |segment| {
+            segment.ident == "MaybeUninit"
+                || (segment.arguments.is_empty()
+                    && [
+                        "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+                        "i128", "isize",
+                    ]
+                    .iter()
+                    .any(|int| segment.ident == int))
+        });
+        if !is_plain {
+            return Err(parse::Error::new_spanned(
+                &item.ty,
+                "`stack` requires an array of integers or of `MaybeUninit<T>`",
+            ));
+        }
+
+        let arg = if args.address.is_some() {
+            Some("address")
+        } else if item.abi.is_some() {
+            Some("extern")
+        } else if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.per_cpu.is_some() {
+            Some("per_cpu")
+        } else if args.pinned {
+            Some("pinned")
+        } else if args.volatile {
+            Some("volatile")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`stack` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if args.reset {
+        if item.mutability.is_none() {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                "`reset` can only be used on `static mut` variables",
+            ));
+        }
+
+        if item.expr.is_none() {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                "`reset` restores the initializer; it can't be used on variables without one",
+            ));
+        }
+
+        let arg = if args.typestate {
+            Some("typestate")
+        } else if args.uninit {
+            Some("uninit")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`reset` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if args.noinit.is_some() {
+        if item.mutability.is_none() {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                "`noinit` can only be used on `static mut` variables",
+            ));
+        }
+
+        if let Some(expr) = item.expr.as_ref() {
+            return Err(parse::Error::new_spanned(
+                expr,
+                "`noinit` variables keep their contents across resets; remove the initializer",
+            ));
+        }
+
+        if maybe_uninit(&item.ty).is_none() {
+            return Err(parse::Error::new_spanned(
+                &item.ty,
+                "`noinit` requires a `MaybeUninit<T>` type",
+            ));
+        }
+
+        let arg = if args.address.is_some() {
+            Some("address")
+        } else if item.abi.is_some() {
+            Some("extern")
+        } else if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.per_cpu.is_some() {
+            Some("per_cpu")
+        } else if args.pinned {
+            Some("pinned")
+        } else if args.section.is_some() {
+            Some("section")
+        } else if args.uninit {
+            Some("uninit")
+        } else if args.volatile {
+            Some("volatile")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`noinit` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if args.typestate {
+        if item.mutability.is_none() {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                "`typestate` can only be used on `static mut` variables",
+            ));
+        }
+
+        if maybe_uninit(&item.ty).is_none() {
+            return Err(parse::Error::new_spanned(
+                &item.ty,
+                "`typestate` requires a `MaybeUninit<T>` type",
+            ));
+        }
+
+        // NOTE the destructor of the proxy would have to handle both states
+        let arg = if args.address.is_some() {
+            Some("address")
+        } else if item.abi.is_some() {
+            Some("extern")
+        } else if args.lazy.is_some() {
+            Some("lazy")
+        } else if args.noinit.is_some() {
+            Some("noinit")
+        } else if args.on_drop.is_some() {
+            Some("on_drop")
+        } else if args.per_cpu.is_some() {
+            Some("per_cpu")
+        } else if args.pinned {
+            Some("pinned")
+        } else if args.release {
+            Some("release")
+        } else if args.split.is_some() {
+            Some("split")
+        } else if args.uninit {
+            Some("uninit")
+        } else if args.volatile {
+            Some("volatile")
+        } else if args.zeroize {
+            Some("zeroize")
+        } else {
+            None
+        };
+
+        if let Some(arg) = arg {
+            return Err(parse::Error::new(
+                item.ident.span(),
+                format!("`typestate` can't be used together with `{}`", arg),
+            ));
+        }
+    }
+
+    if args.uninit {
+        if item.mutability.is_none() {
+            return Err(parse::Error::new(
+                item.static_token.span,
+                "`uninit` can only be used on `static mut` variables",
+            ));
+        }
+
+        if maybe_uninit(&item.ty).is_none() {
+            return Err(parse::Error::new_spanned(
+                &item.ty,
+                "`uninit` requires a `MaybeUninit<T>` type",
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the span of the first lifetime in `tokens` that's neither `'static` nor introduced by
+/// a `for<..>` binder
+fn non_static_lifetime(tokens: TokenStream2, bound: &mut Vec<Ident>) -> Option<Span> {
+    let mut tokens = tokens.into_iter().peekable();
+
+    while let Some(tt) = tokens.next() {
+        match tt {
+            TokenTree::Group(group) => {
+                if let Some(span) = non_static_lifetime(group.stream(), bound) {
+                    return Some(span);
+                }
+            }
+            TokenTree::Ident(ref ident) if ident == "for" => {
+                // for<'a, 'b>
+                let is_binder = match tokens.peek() {
+                    Some(TokenTree::Punct(punct)) => punct.as_char() == '<',
+                    _ => false,
+                };
+
+                if is_binder {
+                    for tt in tokens.by_ref() {
+                        match tt {
+                            TokenTree::Punct(ref punct) if punct.as_char() == '>' => break,
+                            TokenTree::Ident(ident) => bound.push(ident),
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            TokenTree::Punct(ref punct) if punct.as_char() == '\'' => {
+                if let Some(TokenTree::Ident(ident)) = tokens.peek() {
+                    if ident != "static" && !bound.contains(ident) {
+                        return Some(ident.span());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
@@ -0,0 +1,1658 @@
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::quote;
+use syn::{parse, ForeignItem, Ident, IntSuffix, ItemForeignMod, LitInt, Type};
+
+use args::{Args, AutoImpl};
+use check::check;
+use item::Static;
+use util::{array, array_len, maybe_uninit, mk_ident, seed};
+
+/// Expands a `#[Singleton(..)]` `extern` block
+pub fn expand_foreign(args: &Args, item: &ItemForeignMod) -> parse::Result<TokenStream2> {
+    let mut expanded = vec![];
+    let mut others = vec![];
+    for foreign in &item.items {
+        match foreign {
+            ForeignItem::Static(foreign) => {
+                let item = Static::foreign(foreign.clone(), item.abi.clone());
+                expanded.push(expand(args, &item)?);
+            }
+            _ => others.push(foreign),
+        }
+    }
+
+    if !others.is_empty() {
+        let attrs = &item.attrs;
+        let abi = &item.abi;
+        expanded.push(quote!(#(#attrs)* #abi { #(#others)* }));
+    }
+
+    Ok(quote!(#(#expanded)*))
+}
+
+/// Expands a `#[Singleton(..)]` `static [mut]` item
+pub fn expand(args: &Args, item: &Static) -> parse::Result<TokenStream2> {
+    if args.count.is_some() {
+        let expansions = family(args, item)
+            .iter()
+            .map(|(args, item)| expand(args, item))
+            .collect::<parse::Result<Vec<_>>>()?;
+
+        return Ok(quote!(#(#expansions)*));
+    }
+
+    check(item, args)?;
+
+    // documentation goes on the proxy; everything else stays on the `static` variable
+    let (docs, attrs): (Vec<_>, Vec<_>) = item
+        .attrs
+        .iter()
+        .partition(|attr| attr.path.is_ident("doc"));
+    let vis = &item.vis;
+    let ident = args.name.as_ref().unwrap_or(&item.ident);
+    // the proxies of `typestate` variables dereference to the value inside the `MaybeUninit`
+    let ty = if args.typestate {
+        maybe_uninit(&item.ty).unwrap()
+    } else {
+        &item.ty
+    };
+    let expr = &item.expr;
+    let seed = seed(&quote!(#item));
+    let name = item.ident.to_string();
+    let alias = if args.readable {
+        Ident::new(&format!("__{}", name), Span::call_site())
+    } else {
+        mk_ident(&seed, "alias")
+    };
+    let symbol = if let Some(export_name) = args.export_name.as_ref() {
+        quote!(#export_name)
+    } else if args.no_mangle {
+        quote!(#name)
+    } else if args.readable || args.deny_duplicates {
+        quote!(concat!(module_path!(), "::", #name))
+    } else {
+        let symbol = format!("{}::{}", name, alias);
+        quote!(#symbol)
+    };
+
+    let export_name = if args.no_export {
+        None
+    } else if args.deny_duplicates {
+        // keep the variable around, even if unused, so that duplicates always reach the linker
+        Some(quote!(#[export_name = #symbol] #[used]))
+    } else {
+        Some(quote!(#[export_name = #symbol]))
+    };
+
+    let derives = args
+        .derives
+        .as_ref()
+        .map(|derives| quote!(#[derive(#derives)]));
+
+    let marker = if args.pinned {
+        quote!(owned_singleton::export::NotSendOrSyncOrUnpin)
+    } else if args.typestate {
+        quote!(owned_singleton::export::NotSendOrSyncIn<S>)
+    } else {
+        quote!(owned_singleton::export::NotSendOrSync)
+    };
+    // NOTE the default keeps `#ident` referring to the initialized state
+    let params = if args.typestate {
+        Some(quote!(<S = owned_singleton::typestate::Init>))
+    } else {
+        None
+    };
+    let proxy = quote!(
+        #(#docs)*
+        #derives
+        #vis struct #ident #params { #alias: #marker }
+    );
+
+    // NOTE `check` already made sure that the type is `MaybeUninit<T>`
+    let uninit = if args.uninit { maybe_uninit(ty) } else { None };
+
+    let section = if let Some(section) = args.section.as_ref() {
+        Some(quote!(#section))
+    } else if uninit.is_some() || args.noinit.is_some() {
+        // one section per variable so the linker can discard unused ones
+        let section = format!(".uninit.{}", alias);
+        Some(quote!(#section))
+    } else {
+        None
+    };
+    let link_section = section
+        .as_ref()
+        .map(|section| quote!(#[link_section = #section]));
+
+    // lazy variables start uninitialized; they are initialized on first access. Likewise, `init`
+    // variables are initialized by `init`
+    let (storage_ty, expr) = if args.lazy.is_some() || args.init {
+        (
+            quote!(owned_singleton::export::MaybeUninit<#ty>),
+            quote!(owned_singleton::export::MaybeUninit::uninit()),
+        )
+    } else if args.noinit.is_some() {
+        // whatever was in memory before the reset
+        (
+            quote!(#ty),
+            quote!(owned_singleton::export::MaybeUninit::uninit()),
+        )
+    } else if args.typestate {
+        (
+            quote!(owned_singleton::export::MaybeUninit<#ty>),
+            quote!(#expr),
+        )
+    } else {
+        (quote!(#ty), quote!(#expr))
+    };
+
+    // one copy of the variable per core
+    let per_cpu = args
+        .per_cpu
+        .as_ref()
+        .and_then(|n| args.core.as_ref().map(|core| (n, core)));
+    let (storage_ty, expr) = if let Some((n, _)) = per_cpu {
+        let exprs = (0..n.value()).map(|_| &expr);
+        (quote!([#storage_ty; #n]), quote!([#(#exprs),*]))
+    } else {
+        (storage_ty, expr)
+    };
+
+    // immutable variables are stored in a plain `static` so they can be placed in read-only memory
+    let read_only = item.mutability.is_none()
+        && args.lazy.is_none()
+        && args.address.is_none()
+        && item.abi.is_none();
+    // mutable variables are stored in a `Cell`; all accesses go through the pointer it returns
+    let (storage_ty, expr) = if read_only {
+        (
+            quote!(owned_singleton::export::Static<#storage_ty>),
+            quote!(owned_singleton::export::Static(#expr)),
+        )
+    } else {
+        (
+            quote!(owned_singleton::export::Cell<#storage_ty>),
+            quote!(owned_singleton::export::Cell::new(#expr)),
+        )
+    };
+    // the place expression of the value stored in `storage`
+    let contents = |storage: TokenStream2| {
+        if read_only {
+            quote!(#storage.0)
+        } else {
+            quote!((*#storage.get()))
+        }
+    };
+
+    // stacks are 8-byte aligned, as most ABIs require, unless told otherwise
+    let align = if args.stack && args.align.is_none() {
+        Some(LitInt::new(8, IntSuffix::None, Span::call_site()))
+    } else {
+        args.align.clone()
+    };
+
+    let mut items = vec![];
+    // overrides the default `Singleton::ALIGN`
+    let mut align_override = None;
+    // the place expression that refers to the `static mut` variable, plus the address and type of
+    // its storage
+    let (place, storage) = if let Some(address) = args.address.as_ref() {
+        let message = format!("`{}` is placed at a misaligned address", ident);
+
+        items.push(quote!(
+            const _: () = assert!(
+                #address % owned_singleton::export::mem::align_of::<#ty>() == 0,
+                #message
+            );
+        ));
+
+        // the storage is not managed by the compiler
+        (
+            quote!((*(#address as *mut #ty))),
+            (quote!(#address as *const ()), quote!(#ty)),
+        )
+    } else if let Some(abi) = item.abi.as_ref() {
+        // keep linking against the original symbol
+        let link_name = if attrs.iter().any(|attr| attr.path.is_ident("link_name")) {
+            None
+        } else {
+            let name = item.ident.to_string();
+            Some(quote!(#[link_name = #name]))
+        };
+
+        items.push(quote!(
+            #abi {
+                #(#attrs)*
+                #link_name
+                static mut #alias: #ty;
+            }
+        ));
+
+        (quote!(#alias), (addr_of(&alias), quote!(#ty)))
+    } else if let Some(align) = align.as_ref() {
+        let wrapper = Ident::new(&format!("{}Aligned", alias), Span::call_site());
+        // NOTE the copies of a `per_cpu` variable are only as aligned as their type
+        if per_cpu.is_none() {
+            align_override = Some(quote!(
+                const ALIGN: usize = owned_singleton::export::mem::align_of::<#wrapper>();
+            ));
+        }
+
+        items.push(quote!(
+            #[repr(align(#align))]
+            struct #wrapper(#storage_ty);
+
+            #(#attrs)*
+            #export_name
+            #link_section
+            static #alias: #wrapper = #wrapper(#expr);
+        ));
+
+        (
+            contents(quote!(#alias.0)),
+            (addr_of(&alias), quote!(#wrapper)),
+        )
+    } else {
+        items.push(quote!(
+            #(#attrs)*
+            #export_name
+            #link_section
+            static #alias: #storage_ty = #expr;
+        ));
+
+        (contents(quote!(#alias)), (addr_of(&alias), storage_ty))
+    };
+
+    if cfg!(feature = "registry") {
+        let descriptor = mk_ident(&seed, "descriptor");
+        let (address, storage_ty) = storage;
+        let section = match section {
+            Some(section) => quote!(Some(#section)),
+            None => quote!(None),
+        };
+
+        items.push(quote!(
+            #[link_section = "owned_singleton_registry"]
+            #[used]
+            static #descriptor: owned_singleton::registry::Descriptor =
+                owned_singleton::registry::Descriptor {
+                    name: concat!(module_path!(), "::", #name),
+                    address: #address,
+                    size: owned_singleton::export::mem::size_of::<#storage_ty>(),
+                    align: owned_singleton::export::mem::align_of::<#storage_ty>(),
+                    section: #section,
+                };
+        ));
+    }
+
+    let place = if let Some((_, core)) = per_cpu {
+        quote!(#place[#core()])
+    } else {
+        place
+    };
+
+    // `get` must not initialize a lazy variable as it can be called without owning the proxy;
+    // `overrides` holds the `Singleton` methods that do initialize it
+    let mut overrides = None;
+    let (place, get) = if let Some(lazy) = args.lazy.as_ref() {
+        let initialized = mk_ident(&seed, "initialized");
+        let force = mk_ident(&seed, "force");
+        // NOTE `MaybeUninit<T>` has the same layout as `T`
+        let ptr = quote!((owned_singleton::export::ptr::addr_of_mut!(#place) as *mut #ty));
+
+        items.push(quote!(
+            static #initialized: owned_singleton::export::Cell<bool> =
+                owned_singleton::export::Cell::new(false);
+
+            // initializes the variable if this is the first access
+            #[inline]
+            unsafe fn #force() -> *mut #ty {
+                if !*#initialized.get() {
+                    #ptr.write(#lazy());
+                    *#initialized.get() = true;
+                }
+
+                #ptr
+            }
+        ));
+
+        overrides = Some(quote!(
+            #[allow(clippy::forget_non_drop)]
+            #[inline]
+            fn unwrap(self) -> &'static mut Self::Type
+            where
+                Self: Sized + owned_singleton::SingletonMut,
+            {
+                owned_singleton::export::mem::forget(self);
+                unsafe { &mut *#force() }
+            }
+
+            #[inline]
+            unsafe fn steal() -> &'static mut Self::Type
+            where
+                Self: owned_singleton::SingletonMut,
+            {
+                &mut *#force()
+            }
+
+            #[allow(clippy::forget_non_drop)]
+            #[inline]
+            fn unwrap_shared(self) -> &'static Self::Type
+            where
+                Self: Sized,
+            {
+                owned_singleton::export::mem::forget(self);
+                unsafe { &*#force() }
+            }
+        ));
+
+        (quote!((*#force())), ptr)
+    } else {
+        // NOTE no references to the `static` variable are created; the proxy goes through a raw
+        // pointer instead
+        let get = if read_only {
+            quote!((owned_singleton::export::ptr::addr_of!(#place) as *mut #ty))
+        } else if args.typestate || args.init {
+            quote!((owned_singleton::export::ptr::addr_of_mut!(#place) as *mut #ty))
+        } else {
+            quote!(owned_singleton::export::ptr::addr_of_mut!(#place))
+        };
+        (quote!((*#get)), get)
+    };
+
+    if let Some(fields) = args.ffi.as_ref() {
+        let getter = Ident::new(&format!("{}_get", ident), ident.span());
+        let doc = format!(
+            "Returns a pointer to the variable behind the `{}` singleton",
+            ident
+        );
+
+        items.push(quote!(
+            #[doc = #doc]
+            #[no_mangle]
+            pub extern "C" fn #getter() -> *mut #ty {
+                unsafe { #get }
+            }
+        ));
+
+        for field in fields {
+            let name = &field.ident;
+            let fty = &field.ty;
+            let getter = Ident::new(&format!("{}_get_{}", ident, name), name.span());
+            let doc = format!("Reads the `{}` field of the `{}` singleton", name, ident);
+
+            items.push(quote!(
+                #[doc = #doc]
+                #[no_mangle]
+                pub unsafe extern "C" fn #getter() -> #fty {
+                    (*(#get)).#name
+                }
+            ));
+
+            if item.mutability.is_some() {
+                let setter = Ident::new(&format!("{}_set_{}", ident, name), name.span());
+                let doc = format!("Writes the `{}` field of the `{}` singleton", name, ident);
+
+                items.push(quote!(
+                    #[doc = #doc]
+                    #[no_mangle]
+                    pub unsafe extern "C" fn #setter(value: #fty) {
+                        (*(#get)).#name = value;
+                    }
+                ));
+            }
+        }
+    }
+
+    // access counters and hook of the `trace` feature
+    let (trace_read, trace_write) = if cfg!(feature = "trace") && !args.volatile {
+        let counters = mk_ident(&seed, "counters");
+        let name_str = ident.to_string();
+        items.push(quote!(
+            static #counters: owned_singleton::trace::Counters =
+                owned_singleton::trace::Counters::new();
+
+            impl #ident {
+                /// Returns the access counters of this singleton
+                #[inline]
+                #vis fn counters() -> &'static owned_singleton::trace::Counters {
+                    &#counters
+                }
+            }
+        ));
+
+        (
+            quote!(#counters.record(#name_str, owned_singleton::trace::Access::Read);),
+            quote!(#counters.record(#name_str, owned_singleton::trace::Access::Write);),
+        )
+    } else {
+        (quote!(), quote!())
+    };
+
+    if args.volatile {
+        // no `Deref` so no `Singleton` either; all accesses go through volatile operations
+        items.push(quote!(
+            const _: fn() = || {
+                fn assert_copy<T: Copy>() {}
+                assert_copy::<#ty>();
+            };
+
+            impl #ident {
+                /// Creates a new singleton
+                ///
+                /// # Safety
+                ///
+                /// It's UB to create more than one instance of this singleton
+                #[inline]
+                #vis unsafe fn new() -> Self {
+                    #ident { #alias: owned_singleton::export::PhantomData }
+                }
+
+                /// Reads the variable using a volatile load
+                #[inline]
+                #vis fn read(&self) -> #ty {
+                    unsafe { owned_singleton::export::ptr::read_volatile(#get) }
+                }
+            }
+        ));
+
+        if item.mutability.is_some() {
+            items.push(quote!(
+                impl #ident {
+                    /// Writes `value` into the variable using a volatile store
+                    #[inline]
+                    #vis fn write(&mut self, value: #ty) {
+                        unsafe { owned_singleton::export::ptr::write_volatile(#get, value) }
+                    }
+
+                    /// Reads the variable, passes it to `f` and writes the result back using
+                    /// volatile operations
+                    #[inline]
+                    #vis fn modify<F>(&mut self, f: F)
+                    where
+                        F: FnOnce(&mut #ty),
+                    {
+                        let mut value = self.read();
+                        f(&mut value);
+                        self.write(value);
+                    }
+                }
+            ));
+        }
+    } else {
+        let name_str = ident.to_string();
+        // `get` returns the copy of the current core
+        let per_cpu_override = if per_cpu.is_some() {
+            Some(quote!(
+                const PER_CPU: bool = true;
+            ))
+        } else {
+            None
+        };
+        items.push(quote!(
+            unsafe impl owned_singleton::Singleton for #ident {
+                type Type = #ty;
+
+                const NAME: &'static str = #name_str;
+                #align_override
+                #per_cpu_override
+
+                #[inline]
+                unsafe fn new() -> Self {
+                    #ident { #alias: owned_singleton::export::PhantomData }
+                }
+
+                #[inline]
+                fn get() -> *mut Self::Type {
+                    unsafe { #get }
+                }
+
+                #overrides
+            }
+
+            impl owned_singleton::export::Deref for #ident {
+                type Target = #ty;
+
+                #[inline]
+                fn deref(&self) -> &Self::Target {
+                    #trace_read
+                    unsafe { &#place }
+                }
+            }
+
+            unsafe impl owned_singleton::export::StableDeref for #ident {}
+        ));
+
+        items.push(conversions(ident, ty, false));
+    }
+
+    // the address of lazy and `per_cpu` variables is only known at runtime
+    let const_ptr =
+        cfg!(feature = "const-ptr") && !args.volatile && args.lazy.is_none() && per_cpu.is_none();
+    if const_ptr {
+        items.push(quote!(
+            unsafe impl owned_singleton::ConstSingleton for #ident {
+                const PTR: *mut #ty = unsafe { #get };
+            }
+        ));
+    }
+
+    if let Some(crc) = args.noinit {
+        // NOTE `check` already made sure that the type is `MaybeUninit<T>`
+        let inner = maybe_uninit(ty).unwrap();
+        let header = mk_ident(&seed, "header");
+        let section = format!(".uninit.{}.header", alias);
+        items.push(quote!(
+            // the magic number, and checksum, that tell whether the variable holds a valid value
+            #[link_section = #section]
+            static #header: owned_singleton::export::Header =
+                owned_singleton::export::Header::uninit();
+
+            impl #ident {
+                /// Returns `true` if the variable holds a value stored by `write`, e.g. before
+                /// the last reset
+                #[inline]
+                #vis fn is_valid(&self) -> bool {
+                    unsafe { #header.is_valid(#get as *const #inner, #crc) }
+                }
+
+                /// Marks the variable as not holding a valid value
+                #[inline]
+                #vis fn invalidate(&mut self) {
+                    #header.invalidate()
+                }
+
+                /// Stores `value` in the variable, marks it as valid and returns a reference to
+                /// it
+                #[inline]
+                #vis fn write(&mut self, value: #inner) -> &#inner {
+                    unsafe {
+                        let ptr = #get as *mut #inner;
+                        ptr.write(value);
+                        #header.seal(ptr, #crc);
+                        &*ptr
+                    }
+                }
+
+                /// Returns a reference to the value of the variable, if it's valid
+                #[inline]
+                #vis fn valid(&self) -> Option<&#inner> {
+                    if self.is_valid() {
+                        Some(unsafe { &*(#get as *const #inner) })
+                    } else {
+                        None
+                    }
+                }
+
+                /// Modifies the value of the variable, if it's valid, and keeps it marked as valid
+                ///
+                /// Returns `false` if the variable doesn't hold a valid value
+                #[inline]
+                #vis fn modify<F>(&mut self, f: F) -> bool
+                where
+                    F: FnOnce(&mut #inner),
+                {
+                    if !self.is_valid() {
+                        return false;
+                    }
+
+                    unsafe {
+                        let ptr = #get as *mut #inner;
+                        f(&mut *ptr);
+                        #header.seal(ptr, #crc);
+                    }
+
+                    true
+                }
+            }
+        ));
+    }
+
+    if args.typestate {
+        items.push(quote!(
+            impl #ident<owned_singleton::typestate::Uninit> {
+                /// Creates a proxy to the uninitialized variable
+                ///
+                /// # Safety
+                ///
+                /// It's UB to create more than one instance of this singleton, in either state
+                #[inline]
+                #vis unsafe fn uninit() -> Self {
+                    #ident { #alias: owned_singleton::export::PhantomData }
+                }
+
+                /// Initializes the variable with `value`
+                #[inline]
+                #vis fn init(self, value: #ty) -> #ident {
+                    unsafe { #get.write(value) }
+                    #ident { #alias: owned_singleton::export::PhantomData }
+                }
+
+                /// Initializes the variable with the value returned by `f`
+                #[inline]
+                #vis fn init_with<F>(self, f: F) -> #ident
+                where
+                    F: FnOnce() -> #ty,
+                {
+                    self.init(f())
+                }
+            }
+        ));
+    }
+
+    // what `testing::reset_all` does to this singleton
+    let mut resets = vec![];
+
+    if args.init {
+        let state = mk_ident(&seed, "state");
+        let message = format!("`{}` has already been initialized", ident);
+        items.push(quote!(
+            static #state: owned_singleton::export::InitState =
+                owned_singleton::export::InitState::new();
+
+            impl #ident {
+                /// Initializes the variable with `value` and returns the proxy
+                ///
+                /// Returns `value` back if the variable has already been initialized
+                #[inline]
+                #vis fn init(value: #ty) -> Result<Self, #ty> {
+                    if #state.begin() {
+                        unsafe { #get.write(value) }
+                        #state.finish();
+                        Ok(#ident { #alias: owned_singleton::export::PhantomData })
+                    } else {
+                        Err(value)
+                    }
+                }
+
+                /// Initializes the variable with `value` and returns a `&'static mut` reference to
+                /// it, like `StaticCell::init` does
+                ///
+                /// This is meant for APIs that take `&'static mut` references, like task spawners
+                /// and driver constructors.
+                ///
+                /// # Panics
+                ///
+                /// This function panics if the variable has already been initialized
+                #[inline]
+                #vis fn init_for_task(value: #ty) -> &'static mut #ty {
+                    match Self::init(value) {
+                        Ok(proxy) => owned_singleton::Singleton::unwrap(proxy),
+                        Err(_) => panic!(#message),
+                    }
+                }
+
+                /// Returns `true` if the variable has been initialized
+                #[inline]
+                #vis fn is_initialized() -> bool {
+                    #state.is_ready()
+                }
+            }
+        ));
+
+        // NOTE the old value is leaked
+        resets.push(quote!(#state.reset();));
+    }
+
+    // NOTE opt-in because not every initializer is valid in a `const fn` (e.g. `&mut [..]`)
+    if let Some(init) = item.expr.as_ref().filter(|_| args.reset) {
+        items.push(quote!(
+            impl #ident {
+                /// Returns the value the variable behind this proxy is initialized with
+                #[inline]
+                #vis const fn initial() -> #ty {
+                    #init
+                }
+            }
+        ));
+
+        // NOTE `reset` is only accepted on `static mut` variables
+        let reset = if args.volatile {
+            quote!(self.write(Self::initial()))
+        } else if args.pinned {
+            quote!(self.as_pin_mut().set(Self::initial()))
+        } else {
+            quote!(**self = Self::initial())
+        };
+
+        // NOTE per-CPU variables can only be reached from their own core
+        if per_cpu.is_none() {
+            resets.push(if args.volatile {
+                quote!(owned_singleton::export::ptr::write_volatile(#get, #ident::initial());)
+            } else {
+                quote!(*#get = #ident::initial();)
+            });
+        }
+
+        items.push(quote!(
+            impl #ident {
+                /// Restores the initial value of the variable behind this proxy
+                #[inline]
+                #vis fn reset(&mut self) {
+                    #reset
+                }
+            }
+        ));
+    }
+
+    // spares array users the `&mut *proxy` dance
+    if let Some(elem) = array(ty).filter(|_| !args.volatile) {
+        items.push(quote!(
+            impl #ident {
+                /// Returns the array behind this proxy as a slice
+                #[inline]
+                #vis fn as_slice(&self) -> &[#elem] {
+                    &**self
+                }
+
+                /// Returns the number of elements in the array behind this proxy
+                #[inline]
+                #vis fn len(&self) -> usize {
+                    self.as_slice().len()
+                }
+
+                /// Returns `true` if the array behind this proxy has no elements
+                #[inline]
+                #vis fn is_empty(&self) -> bool {
+                    self.as_slice().is_empty()
+                }
+
+                /// Returns an iterator over the elements of the array behind this proxy
+                #[inline]
+                #vis fn iter(&self) -> owned_singleton::export::slice::Iter<#elem> {
+                    self.as_slice().iter()
+                }
+            }
+        ));
+
+        if item.mutability.is_some() && !args.pinned {
+            items.push(quote!(
+                impl #ident {
+                    /// Returns the array behind this proxy as a mutable slice
+                    #[inline]
+                    #vis fn as_mut_slice(&mut self) -> &mut [#elem] {
+                        &mut **self
+                    }
+
+                    /// Returns an iterator that allows modifying the elements of the array behind
+                    /// this proxy
+                    #[inline]
+                    #vis fn iter_mut(&mut self) -> owned_singleton::export::slice::IterMut<#elem> {
+                        self.as_mut_slice().iter_mut()
+                    }
+                }
+            ));
+
+            // per core copies live at different addresses so chunks can't be derived from `get`
+            if args.split.is_none() && args.per_cpu.is_none() {
+                items.push(quote!(
+                    impl #ident {
+                        /// Splits the array behind this proxy into `M` chunks of `CHUNK`
+                        /// elements, each owned by its own handle
+                        #[inline]
+                        #vis fn split<const CHUNK: usize, const M: usize>(
+                            self,
+                        ) -> [owned_singleton::buffer::Chunk<#ident, CHUNK>; M] {
+                            owned_singleton::buffer::Chunk::split(self)
+                        }
+                    }
+                ));
+            }
+        }
+    }
+
+    if args.stack {
+        // NOTE `check` already made sure that the type is an array
+        let elem = array(ty);
+        items.push(quote!(
+            impl #ident {
+                /// Returns a pointer to the lowest address of the stack
+                #[inline]
+                #vis fn bottom(&self) -> *mut #elem {
+                    <Self as owned_singleton::Singleton>::get() as *mut #elem
+                }
+
+                /// Returns a pointer one past the highest address of the stack, where a full
+                /// descending stack starts
+                #[inline]
+                #vis fn top(&self) -> *mut #elem {
+                    unsafe { self.bottom().add(self.len()) }
+                }
+
+                /// Returns the size of the stack in bytes
+                #[inline]
+                #vis fn size(&self) -> usize {
+                    owned_singleton::export::mem::size_of::<#ty>()
+                }
+            }
+        ));
+
+        if let Some(paint) = args.paint.as_ref() {
+            items.push(quote!(
+                impl #ident {
+                    /// The byte pattern `paint` fills the stack with
+                    #vis const PAINT: u8 = #paint;
+
+                    /// Fills the stack with `PAINT`
+                    #[inline]
+                    #vis fn paint(&mut self) {
+                        unsafe {
+                            owned_singleton::export::ptr::write_bytes(
+                                self.bottom() as *mut u8,
+                                Self::PAINT,
+                                self.size(),
+                            )
+                        }
+                    }
+
+                    /// Returns the number of bytes of the stack that have been used since it was
+                    /// last painted
+                    ///
+                    /// The stack is assumed to grow downwards, from `top` towards `bottom`
+                    #[inline]
+                    #vis fn high_water_mark(&self) -> usize {
+                        let bottom = self.bottom() as *const u8;
+                        // NOTE the stack may be in use by another context
+                        let untouched = (0..self.size())
+                            .take_while(|i| unsafe {
+                                owned_singleton::export::ptr::read_volatile(bottom.add(*i))
+                                    == Self::PAINT
+                            })
+                            .count();
+
+                        self.size() - untouched
+                    }
+                }
+            ));
+        }
+    }
+
+    // format, compare and hash the variable, not the proxy
+    for forward in &args.forward {
+        items.push(match &*forward.to_string() {
+            "Debug" | "Display" => quote!(
+                impl owned_singleton::export::fmt::#forward for #ident {
+                    fn fmt(
+                        &self,
+                        f: &mut owned_singleton::export::fmt::Formatter,
+                    ) -> owned_singleton::export::fmt::Result {
+                        owned_singleton::export::fmt::#forward::fmt(&**self, f)
+                    }
+                }
+            ),
+            "Eq" => quote!(
+                impl owned_singleton::export::cmp::Eq for #ident {}
+            ),
+            "Hash" => quote!(
+                impl owned_singleton::export::hash::Hash for #ident {
+                    fn hash<H>(&self, state: &mut H)
+                    where
+                        H: owned_singleton::export::hash::Hasher,
+                    {
+                        owned_singleton::export::hash::Hash::hash(&**self, state)
+                    }
+                }
+            ),
+            "Ord" => quote!(
+                impl owned_singleton::export::cmp::Ord for #ident {
+                    fn cmp(&self, other: &Self) -> owned_singleton::export::cmp::Ordering {
+                        owned_singleton::export::cmp::Ord::cmp(&**self, &**other)
+                    }
+                }
+            ),
+            "PartialEq" => quote!(
+                impl owned_singleton::export::cmp::PartialEq for #ident {
+                    fn eq(&self, other: &Self) -> bool {
+                        owned_singleton::export::cmp::PartialEq::eq(&**self, &**other)
+                    }
+                }
+            ),
+            "PartialOrd" => quote!(
+                impl owned_singleton::export::cmp::PartialOrd for #ident {
+                    fn partial_cmp(
+                        &self,
+                        other: &Self,
+                    ) -> Option<owned_singleton::export::cmp::Ordering> {
+                        owned_singleton::export::cmp::PartialOrd::partial_cmp(&**self, &**other)
+                    }
+                }
+            ),
+            _ => unreachable!(),
+        });
+    }
+
+    if cfg!(feature = "defmt") && !args.volatile {
+        // NOTE the binder keeps the bound from being rejected when `#ty` doesn't implement
+        // `Format`; the impl simply doesn't apply then
+        items.push(quote!(
+            impl owned_singleton::export::Format for #ident
+            where
+                for<'a> #ty: owned_singleton::export::Format,
+            {
+                #[inline]
+                fn format(&self, fmt: owned_singleton::export::Formatter) {
+                    owned_singleton::export::Format::format(&**self, fmt)
+                }
+            }
+        ));
+    }
+
+    if cfg!(feature = "ufmt") && !args.volatile {
+        // NOTE the binders keep the bounds from being rejected when `#ty` doesn't implement the
+        // traits; the impls simply don't apply then
+        items.push(quote!(
+            impl owned_singleton::export::uDebug for #ident
+            where
+                for<'a> #ty: owned_singleton::export::uDebug,
+            {
+                #[inline]
+                fn fmt<W>(
+                    &self,
+                    f: &mut owned_singleton::export::UFormatter<W>,
+                ) -> Result<(), W::Error>
+                where
+                    W: owned_singleton::export::uWrite + ?Sized,
+                {
+                    owned_singleton::export::uDebug::fmt(&**self, f)
+                }
+            }
+
+            impl owned_singleton::export::uDisplay for #ident
+            where
+                for<'a> #ty: owned_singleton::export::uDisplay,
+            {
+                #[inline]
+                fn fmt<W>(
+                    &self,
+                    f: &mut owned_singleton::export::UFormatter<W>,
+                ) -> Result<(), W::Error>
+                where
+                    W: owned_singleton::export::uWrite + ?Sized,
+                {
+                    owned_singleton::export::uDisplay::fmt(&**self, f)
+                }
+            }
+        ));
+    }
+
+    if cfg!(feature = "log") && !args.volatile {
+        // NOTE the binder keeps the bound from being rejected when `#ty` doesn't implement `Log`;
+        // the method simply can't be called then
+        items.push(quote!(
+            impl #ident {
+                /// Registers the variable as the global logger of the `log` crate
+                ///
+                /// The proxy is consumed, even if a logger has already been registered; the
+                /// logger keeps shared access to the variable for the rest of the program.
+                #[inline]
+                #vis fn set_as_logger(self) -> Result<(), owned_singleton::export::SetLoggerError>
+                where
+                    for<'a> #ty: owned_singleton::export::Log,
+                {
+                    owned_singleton::export::set_logger(owned_singleton::Singleton::unwrap_shared(
+                        self,
+                    ))
+                }
+            }
+        ));
+    }
+
+    // NOTE the proxy owns the variable so "locking" it doesn't need a critical section
+    if cfg!(feature = "rtic") && item.mutability.is_some() && !args.pinned && !args.volatile {
+        items.push(quote!(
+            impl owned_singleton::export::Mutex for #ident {
+                type T = #ty;
+
+                #[inline]
+                fn lock<R>(&mut self, f: impl FnOnce(&mut #ty) -> R) -> R {
+                    f(&mut **self)
+                }
+            }
+        ));
+    }
+
+    if cfg!(feature = "serde") && !args.volatile {
+        // NOTE the binder keeps the bound from being rejected when `#ty` doesn't implement
+        // `Serialize`; the impl simply doesn't apply then
+        items.push(quote!(
+            impl owned_singleton::export::Serialize for #ident
+            where
+                for<'a> #ty: owned_singleton::export::Serialize,
+            {
+                #[inline]
+                fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+                where
+                    S: owned_singleton::export::Serializer,
+                {
+                    owned_singleton::export::Serialize::serialize(&**self, serializer)
+                }
+            }
+        ));
+    }
+
+    if args.refcount {
+        items.push(quote!(
+            unsafe impl owned_singleton::RefCounted for #ident {
+                #[inline]
+                fn count() -> &'static owned_singleton::export::RefCount {
+                    static COUNT: owned_singleton::export::RefCount =
+                        owned_singleton::export::RefCount::new();
+
+                    &COUNT
+                }
+            }
+        ));
+    }
+
+    if let Some(max_size) = args.max_size.as_ref() {
+        let message = format!(
+            "`{}` exceeds its `max_size` budget of {} bytes",
+            ident,
+            max_size.value()
+        );
+
+        items.push(quote!(
+            const _: () = assert!(
+                owned_singleton::export::mem::size_of::<#ty>() <= #max_size,
+                #message
+            );
+        ));
+    }
+
+    // proxies of immutable variables only hand out shared references so they can be duplicated,
+    // unless dropping them or dereferencing them has side effects
+    let copy = item.mutability.is_none() && !args.release && args.lazy.is_none();
+    if copy {
+        items.push(quote!(
+            impl Clone for #ident {
+                #[inline]
+                fn clone(&self) -> Self {
+                    *self
+                }
+            }
+
+            impl Copy for #ident {}
+        ));
+
+        // NOTE volatile proxies already have an inherent (`unsafe`) constructor
+        if !args.volatile {
+            items.push(quote!(
+                impl #ident {
+                    /// Creates a new proxy
+                    ///
+                    /// Unlike `Singleton::new` this constructor is safe as the proxy only hands
+                    /// out shared references
+                    #[inline]
+                    #vis fn new() -> Self {
+                        #ident { #alias: owned_singleton::export::PhantomData }
+                    }
+                }
+            ));
+        }
+    }
+
+    match args.send {
+        // copies of the proxy may end up in different threads
+        Some(AutoImpl::Bounded) if copy => items.push(quote!(
+            unsafe impl Send for #ident where #ty: Sync {}
+        )),
+        Some(AutoImpl::Bounded) => items.push(quote!(
+            unsafe impl Send for #ident where #ty: Send {}
+        )),
+        Some(AutoImpl::Forced) => items.push(quote!(
+            unsafe impl Send for #ident {}
+        )),
+        None => {}
+    }
+
+    match args.sync {
+        Some(AutoImpl::Bounded) => items.push(quote!(
+            unsafe impl Sync for #ident where #ty: Sync {}
+        )),
+        Some(AutoImpl::Forced) => items.push(quote!(
+            unsafe impl Sync for #ident {}
+        )),
+        None => {}
+    }
+
+    // statements that run, in order, when the proxy is dropped
+    let mut on_drop = vec![];
+    if let Some(hook) = args.on_drop.as_ref() {
+        on_drop.push(quote!(#hook(owned_singleton::export::DerefMut::deref_mut(self));));
+    }
+
+    if args.zeroize {
+        on_drop.push(quote!(unsafe {
+            owned_singleton::export::wipe(<Self as owned_singleton::Singleton>::get())
+        }));
+
+        if cfg!(feature = "zeroize") {
+            items.push(quote!(
+                impl owned_singleton::export::ZeroizeOnDrop for #ident {}
+            ));
+        }
+    }
+
+    // `init` is the checked constructor of `init` variables
+    if cfg!(feature = "take") && !args.init {
+        let taken = mk_ident(&seed, "taken");
+        let flag = if let Some((n, core)) = per_cpu {
+            items.push(quote!(
+                static #taken: [owned_singleton::export::Flag; #n] = {
+                    const FLAG: owned_singleton::export::Flag = owned_singleton::export::Flag::new();
+                    [FLAG; #n]
+                };
+            ));
+
+            resets.push(quote!(
+                for flag in &#taken {
+                    flag.release();
+                }
+            ));
+
+            quote!(#taken[#core()])
+        } else {
+            items.push(quote!(
+                static #taken: owned_singleton::export::Flag = owned_singleton::export::Flag::new();
+            ));
+
+            resets.push(quote!(#taken.release();));
+
+            quote!(#taken)
+        };
+
+        // the slot that records where the singleton was taken
+        let owner = if cfg!(feature = "track-owner") {
+            Some(mk_ident(&seed, "owner"))
+        } else {
+            None
+        };
+
+        // statements that make the singleton available to `take` again
+        let mut give_back = vec![];
+        // NOTE forget the location before another context can take the singleton
+        if let Some(owner) = owner.as_ref() {
+            give_back.push(quote!(#owner.clear();));
+        }
+
+        // one flag per element so the elements can be taken one by one
+        let mut take_elements = None;
+        let len = array_len(ty).filter(|_| {
+            item.mutability.is_some()
+                && args.lazy.is_none()
+                && args.per_cpu.is_none()
+                && args.split.is_none()
+                && !args.pinned
+                && !args.typestate
+                && !args.volatile
+        });
+        if let Some(len) = len {
+            let elements = mk_ident(&seed, "elements");
+            let release = args.release;
+            items.push(quote!(
+                static #elements: [owned_singleton::export::Flag; #len] = {
+                    const FLAG: owned_singleton::export::Flag = owned_singleton::export::Flag::new();
+                    [FLAG; #len]
+                };
+
+                unsafe impl owned_singleton::export::Elements for #ident {
+                    const RELEASE: bool = #release;
+
+                    #[inline]
+                    fn flags() -> &'static [owned_singleton::export::Flag] {
+                        &#elements
+                    }
+                }
+
+                impl #ident {
+                    /// Takes the element at `index`
+                    ///
+                    /// Returns `None` if `index` is out of bounds, or if the element, or the whole
+                    /// array, has already been taken
+                    #[inline]
+                    #vis fn take_nth(index: usize) -> Option<owned_singleton::buffer::Element<Self>> {
+                        owned_singleton::buffer::Element::take(index)
+                    }
+                }
+            ));
+
+            // the proxy owns every element
+            take_elements = Some(quote!(
+                if !owned_singleton::export::acquire_all(&#elements) {
+                    #flag.release();
+                    return None;
+                }
+            ));
+
+            give_back.push(quote!(owned_singleton::export::release_all(&#elements);));
+            resets.push(quote!(owned_singleton::export::release_all(&#elements);));
+        }
+
+        give_back.push(quote!(#flag.release();));
+        if args.release {
+            on_drop.extend(give_back.iter().cloned());
+        }
+
+        // the variable of a `typestate` singleton must be initialized after taking it
+        let state = if args.typestate {
+            Some(quote!(<owned_singleton::typestate::Uninit>))
+        } else {
+            None
+        };
+
+        // where the singleton was taken
+        let (track_caller, record) = if let Some(owner) = owner {
+            let name_str = ident.to_string();
+            items.push(quote!(
+                static #owner: owned_singleton::export::Owner = owned_singleton::export::Owner::new();
+
+                impl #ident #state {
+                    /// Returns the location of the `take` call that took the singleton, if it has
+                    /// been taken
+                    #[inline]
+                    #vis fn taken_at() -> Option<&'static owned_singleton::export::Location<'static>> {
+                        #owner.get()
+                    }
+
+                    /// Takes the singleton
+                    ///
+                    /// The error reports where the singleton was taken if it has already been
+                    #[inline]
+                    #[track_caller]
+                    #vis fn try_take() -> Result<Self, owned_singleton::TakeError> {
+                        match Self::take() {
+                            Some(proxy) => Ok(proxy),
+                            None => Err(owned_singleton::TakeError::new(#name_str, #owner.get())),
+                        }
+                    }
+                }
+            ));
+
+            resets.push(quote!(#owner.clear();));
+
+            (
+                Some(quote!(#[track_caller])),
+                Some(quote!(#owner.set(owned_singleton::export::Location::caller());)),
+            )
+        } else {
+            (None, None)
+        };
+
+        items.push(quote!(
+            impl #ident #state {
+                /// Takes the singleton
+                ///
+                /// Returns `None` if the singleton has already been taken
+                #[inline]
+                #track_caller
+                #vis fn take() -> Option<Self> {
+                    if #flag.acquire() {
+                        #take_elements
+                        #record
+                        Some(#ident { #alias: owned_singleton::export::PhantomData })
+                    } else {
+                        None
+                    }
+                }
+
+                // used by `Resources::take` to undo a partial take
+                #[doc(hidden)]
+                #[allow(clippy::forget_non_drop)]
+                #[inline]
+                #vis fn __give_back(self) {
+                    owned_singleton::export::mem::forget(self);
+                    #(#give_back)*
+                }
+            }
+        ));
+    }
+
+    if cfg!(feature = "std") && !resets.is_empty() {
+        let reset = mk_ident(&seed, "reset");
+        // NOTE only test builds of the crate that declares the singleton register the reset, and
+        // only on targets where `testing::reset_all` can find the linker section
+        items.push(quote!(
+            #[cfg(all(
+                test,
+                any(
+                    target_os = "android",
+                    target_os = "freebsd",
+                    target_os = "fuchsia",
+                    target_os = "illumos",
+                    target_os = "linux",
+                    target_os = "openbsd"
+                )
+            ))]
+            #[link_section = "owned_singleton_reset"]
+            #[used]
+            static #reset: unsafe fn() = {
+                unsafe fn reset() {
+                    #(#resets)*
+                }
+
+                reset
+            };
+        ));
+    }
+
+    if !on_drop.is_empty() {
+        items.push(quote!(
+            impl Drop for #ident {
+                #[inline]
+                fn drop(&mut self) {
+                    #(#on_drop)*
+                }
+            }
+        ));
+    }
+
+    if let Some(inner) = uninit {
+        items.push(quote!(
+            impl #ident {
+                /// Fills the buffer with zeros and returns a reference to its contents
+                ///
+                /// # Safety
+                ///
+                /// The all-zeros bit pattern must be a valid value of the buffer type
+                #[inline]
+                #vis unsafe fn init_zeroed(&mut self) -> &mut #inner {
+                    let slot = &mut **self;
+                    owned_singleton::export::ptr::write_bytes(slot.as_mut_ptr(), 0, 1);
+                    &mut *slot.as_mut_ptr()
+                }
+
+                /// Initializes the buffer with `value` and returns a reference to its contents
+                #[inline]
+                #vis fn write(&mut self, value: #inner) -> &mut #inner {
+                    let slot: &mut owned_singleton::export::MaybeUninit<#inner> = &mut **self;
+                    slot.write(value)
+                }
+            }
+        ));
+    }
+
+    if args.pinned {
+        items.push(quote!(
+            impl #ident {
+                /// Returns a pinned mutable reference to the variable behind this proxy
+                #[inline]
+                #vis fn as_pin_mut(&mut self) -> owned_singleton::export::Pin<&mut #ty> {
+                    unsafe { owned_singleton::export::Pin::new_unchecked(&mut #place) }
+                }
+
+                /// Consumes this proxy and returns a pinned `&'static mut` reference to the
+                /// variable behind it
+                #[allow(clippy::forget_non_drop)]
+                #[inline]
+                #vis fn unwrap_pinned(self) -> owned_singleton::export::Pin<&'static mut #ty> {
+                    owned_singleton::export::mem::forget(self);
+                    unsafe { owned_singleton::export::Pin::new_unchecked(&mut #place) }
+                }
+            }
+        ));
+    } else if item.mutability.is_some() && !args.volatile {
+        items.push(quote!(
+            impl owned_singleton::export::DerefMut for #ident {
+                #[inline]
+                fn deref_mut(&mut self) -> &mut Self::Target {
+                    #trace_write
+                    unsafe { &mut #place }
+                }
+            }
+
+            unsafe impl owned_singleton::SingletonMut for #ident {}
+        ));
+
+        items.push(conversions(ident, ty, true));
+
+        if cfg!(feature = "zeroize") {
+            // NOTE the binder keeps the bound from being rejected when `#ty` doesn't implement
+            // `Zeroize`; the impl simply doesn't apply then
+            items.push(quote!(
+                impl owned_singleton::export::Zeroize for #ident
+                where
+                    for<'a> #ty: owned_singleton::export::Zeroize,
+                {
+                    #[inline]
+                    fn zeroize(&mut self) {
+                        owned_singleton::export::Zeroize::zeroize(&mut **self)
+                    }
+                }
+            ));
+        }
+    }
+
+    if let Some(fields) = args.split.as_ref() {
+        let mut parts = vec![];
+        for field in fields {
+            let name = &field.ident;
+            let fty = &field.ty;
+            // NOTE call site span so the name doesn't trip the `non_camel_case_types` lint
+            let part = Ident::new(
+                &format!("{}_{}", ident, name.to_string().to_uppercase()),
+                Span::call_site(),
+            );
+            let part_str = part.to_string();
+            let doc = format!("Owned proxy for the `{}` field of [`{}`]", name, ident);
+            let get = if read_only {
+                quote!((owned_singleton::export::ptr::addr_of!(#place.#name) as *mut #fty))
+            } else {
+                quote!(owned_singleton::export::ptr::addr_of_mut!(#place.#name))
+            };
+
+            items.push(quote!(
+                #[doc = #doc]
+                #vis struct #part { #alias: owned_singleton::export::NotSendOrSync }
+
+                unsafe impl owned_singleton::Singleton for #part {
+                    type Type = #fty;
+
+                    const NAME: &'static str = #part_str;
+
+                    #[inline]
+                    unsafe fn new() -> Self {
+                        #part { #alias: owned_singleton::export::PhantomData }
+                    }
+
+                    #[inline]
+                    fn get() -> *mut Self::Type {
+                        unsafe { #get }
+                    }
+                }
+
+                impl owned_singleton::export::Deref for #part {
+                    type Target = #fty;
+
+                    #[inline]
+                    fn deref(&self) -> &Self::Target {
+                        unsafe { &#place.#name }
+                    }
+                }
+
+                unsafe impl owned_singleton::export::StableDeref for #part {}
+            ));
+
+            items.push(conversions(&part, fty, false));
+
+            if const_ptr {
+                items.push(quote!(
+                    unsafe impl owned_singleton::ConstSingleton for #part {
+                        const PTR: *mut #fty = unsafe { #get };
+                    }
+                ));
+            }
+
+            if item.mutability.is_some() {
+                items.push(quote!(
+                    impl owned_singleton::export::DerefMut for #part {
+                        #[inline]
+                        fn deref_mut(&mut self) -> &mut Self::Target {
+                            unsafe { &mut #place.#name }
+                        }
+                    }
+
+                    unsafe impl owned_singleton::SingletonMut for #part {}
+                ));
+
+                items.push(conversions(&part, fty, true));
+            }
+
+            match args.send {
+                Some(AutoImpl::Bounded) => items.push(quote!(
+                    unsafe impl Send for #part where #fty: Send {}
+                )),
+                Some(AutoImpl::Forced) => items.push(quote!(
+                    unsafe impl Send for #part {}
+                )),
+                None => {}
+            }
+
+            match args.sync {
+                Some(AutoImpl::Bounded) => items.push(quote!(
+                    unsafe impl Sync for #part where #fty: Sync {}
+                )),
+                Some(AutoImpl::Forced) => items.push(quote!(
+                    unsafe impl Sync for #part {}
+                )),
+                None => {}
+            }
+
+            parts.push(part);
+        }
+
+        let ctors = parts.clone();
+        items.push(quote!(
+            impl #ident {
+                /// Splits the singleton into one proxy per field
+                ///
+                /// The singleton is *not* released when the field proxies are dropped
+                #[allow(clippy::forget_non_drop)]
+                #[inline]
+                #vis fn split(self) -> (#(#parts,)*) {
+                    owned_singleton::export::mem::forget(self);
+
+                    unsafe { (#(<#ctors as owned_singleton::Singleton>::new(),)*) }
+                }
+            }
+        ));
+    }
+
+    if args.no_export {
+        // the storage can't be named from outside this anonymous `const` item
+        Ok(quote!(
+            #proxy
+
+            const _: () = {
+                #(#items)*
+            };
+        ))
+    } else {
+        Ok(quote!(#proxy #(#items)*))
+    }
+}
+
+/// Returns the names of the proxies generated for the given item
+pub fn proxies(args: &Args, item: &Static) -> Vec<Ident> {
+    if args.count.is_some() {
+        family(args, item)
+            .iter()
+            .flat_map(|(args, item)| proxies(args, item))
+            .collect()
+    } else {
+        vec![args.name.clone().unwrap_or_else(|| item.ident.clone())]
+    }
+}
+
+/// Splits a `count = N` item into N numbered items
+fn family(args: &Args, item: &Static) -> Vec<(Args, Static)> {
+    let count = args.count.as_ref().map(|count| count.value()).unwrap_or(1);
+
+    (0..count)
+        .map(|i| {
+            let suffix = |ident: &Ident| Ident::new(&format!("{}{}", ident, i), ident.span());
+
+            let mut args = args.clone();
+            args.count = None;
+            args.name = args.name.as_ref().map(suffix);
+
+            let mut item = item.clone();
+            item.ident = suffix(&item.ident);
+
+            (args, item)
+        })
+        .collect()
+}
+
+/// Returns an expression that evaluates to the address of the `static` variable `ident`
+fn addr_of(ident: &Ident) -> TokenStream2 {
+    quote!(owned_singleton::export::ptr::addr_of!(#ident) as *const ())
+}
+
+/// `AsRef`, `Borrow` and `Index` implementations, or `AsMut`, `BorrowMut` and `IndexMut` ones if
+/// `mutable`, that forward to the `Deref[Mut]` implementation of the proxy
+fn conversions(proxy: &Ident, ty: &Type, mutable: bool) -> TokenStream2 {
+    if mutable {
+        quote!(
+            impl owned_singleton::export::AsMut<#ty> for #proxy {
+                #[inline]
+                fn as_mut(&mut self) -> &mut #ty {
+                    &mut **self
+                }
+            }
+
+            impl owned_singleton::export::BorrowMut<#ty> for #proxy {
+                #[inline]
+                fn borrow_mut(&mut self) -> &mut #ty {
+                    &mut **self
+                }
+            }
+
+            impl owned_singleton::export::From<#proxy> for &'static mut #ty {
+                #[inline]
+                fn from(proxy: #proxy) -> Self {
+                    owned_singleton::Singleton::leak(proxy)
+                }
+            }
+
+            // NOTE the impl doesn't apply if `#ty` can't be indexed
+            impl<I> owned_singleton::export::IndexMut<I> for #proxy
+            where
+                #ty: owned_singleton::export::IndexMut<I>,
+            {
+                #[inline]
+                fn index_mut(&mut self, index: I) -> &mut Self::Output {
+                    &mut (**self)[index]
+                }
+            }
+        )
+    } else {
+        quote!(
+            impl owned_singleton::export::AsRef<#ty> for #proxy {
+                #[inline]
+                fn as_ref(&self) -> &#ty {
+                    &**self
+                }
+            }
+
+            impl owned_singleton::export::Borrow<#ty> for #proxy {
+                #[inline]
+                fn borrow(&self) -> &#ty {
+                    &**self
+                }
+            }
+
+            // NOTE the impl doesn't apply if `#ty` can't be indexed
+            impl<I> owned_singleton::export::Index<I> for #proxy
+            where
+                #ty: owned_singleton::export::Index<I>,
+            {
+                type Output = <#ty as owned_singleton::export::Index<I>>::Output;
+
+                #[inline]
+                fn index(&self, index: I) -> &Self::Output {
+                    &(**self)[index]
+                }
+            }
+        )
+    }
+}
@@ -0,0 +1,144 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{
+    parenthesized,
+    parse::{self, ParseStream, Parser},
+    Attribute, Ident, Item, ItemMod,
+};
+
+use args::Args;
+use check::check_member;
+use expand::{self, expand};
+use item::Static;
+
+/// Expands a `#[Singletons]` module
+pub fn expand_mod(item: &ItemMod) -> parse::Result<TokenStream2> {
+    let (brace, items) = match item.content.as_ref() {
+        Some(content) => content,
+        None => {
+            return Err(parse::Error::new_spanned(
+                item,
+                "`#[Singletons]` must be applied to a module with a body",
+            ))
+        }
+    };
+
+    let mut expanded = vec![];
+    let mut fields = vec![];
+    let mut proxies = vec![];
+    for item in items {
+        let mut item = match item {
+            Item::Static(item) => Static::from(item.clone()),
+            _ => {
+                expanded.push(quote!(#item));
+                continue;
+            }
+        };
+
+        // an optional `#[Singleton(..)]` attribute provides the per item arguments
+        let mut args = Args::default();
+        let mut attrs = vec![];
+        for attr in item.attrs.drain(..) {
+            if attr.path.is_ident("Singleton") {
+                args = parse_args(&attr)?;
+            } else {
+                attrs.push(attr);
+            }
+        }
+        item.attrs = attrs;
+
+        check_member(&item, &args)?;
+
+        for proxy in expand::proxies(&args, &item) {
+            let field = Ident::new(&proxy.to_string().to_lowercase(), proxy.span());
+            let vis = &item.vis;
+            fields.push(quote!(#vis #field: #proxy));
+            proxies.push((field, proxy));
+        }
+
+        expanded.push(expand(&args, &item)?);
+    }
+
+    let (fields_new, proxies_new): (Vec<_>, Vec<_>) = proxies.iter().cloned().unzip();
+    let mut resources = vec![quote!(
+        /// The singletons declared in this module
+        pub struct Resources {
+            #(#fields,)*
+        }
+
+        impl Resources {
+            /// Creates all the singletons declared in this module
+            ///
+            /// # Safety
+            ///
+            /// It's UB to create more than one instance of any of these singletons
+            #[inline]
+            pub unsafe fn new() -> Self {
+                Resources {
+                    #(#fields_new: <#proxies_new as owned_singleton::Singleton>::new(),)*
+                }
+            }
+        }
+    )];
+
+    if cfg!(feature = "take") {
+        let takes = proxies
+            .iter()
+            .map(|(field, proxy)| quote!(let #field = #proxy::take();));
+        let names = proxies.iter().map(|(field, _)| field).collect::<Vec<_>>();
+        let (taken, fields_init, values, given_back) = (&names, &names, &names, &names);
+
+        resources.push(quote!(
+            impl Resources {
+                /// Takes all the singletons declared in this module
+                ///
+                /// Returns `None` if any of the singletons has already been taken
+                #[inline]
+                pub fn take() -> Option<Self> {
+                    #(#takes)*
+
+                    if true #(&& #taken.is_some())* {
+                        Some(Resources {
+                            #(#fields_init: #values.unwrap(),)*
+                        })
+                    } else {
+                        // give back the singletons that were taken so they can be taken again
+                        #(
+                            if let Some(proxy) = #given_back {
+                                proxy.__give_back();
+                            }
+                        )*
+
+                        None
+                    }
+                }
+            }
+        ));
+    }
+
+    let attrs = &item.attrs;
+    let vis = &item.vis;
+    let mod_token = &item.mod_token;
+    let ident = &item.ident;
+    let mut body = TokenStream2::new();
+    brace.surround(&mut body, |tokens| {
+        tokens.extend(quote!(#(#expanded)* #(#resources)*));
+    });
+
+    Ok(quote!(#(#attrs)* #vis #mod_token #ident #body))
+}
+
+/// Parses the arguments of an inner `#[Singleton(..)]` attribute
+fn parse_args(attr: &Attribute) -> parse::Result<Args> {
+    let parser = |input: ParseStream| {
+        if input.is_empty() {
+            return Ok(Args::default());
+        }
+
+        let content;
+        parenthesized!(content in input);
+        content.parse()
+    };
+
+    parser.parse2(attr.tts.clone())
+}
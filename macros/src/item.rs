@@ -0,0 +1,76 @@
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{ToTokens, TokenStreamExt};
+use syn::{Abi, Attribute, Expr, ForeignItemStatic, Ident, ItemStatic, Token, Type, Visibility};
+
+/// A `static [mut]` variable, either defined in Rust or declared in an `extern` block
+#[derive(Clone)]
+pub struct Static {
+    pub attrs: Vec<Attribute>,
+    pub vis: Visibility,
+    pub static_token: Token![static],
+    pub mutability: Option<Token![mut]>,
+    pub ident: Ident,
+    pub ty: Box<Type>,
+    /// The initializer; `None` if the variable is declared in an `extern` block or is lazily
+    /// initialized
+    pub expr: Option<Box<Expr>>,
+    /// The ABI of the `extern` block the variable is declared in
+    pub abi: Option<Abi>,
+}
+
+impl Static {
+    /// Converts a variable declared in an `extern` block with the given ABI
+    pub fn foreign(item: ForeignItemStatic, abi: Abi) -> Self {
+        Static {
+            abi: Some(abi),
+            ..Static::from(item)
+        }
+    }
+}
+
+impl From<ForeignItemStatic> for Static {
+    fn from(item: ForeignItemStatic) -> Self {
+        Static {
+            attrs: item.attrs,
+            vis: item.vis,
+            static_token: item.static_token,
+            mutability: item.mutability,
+            ident: item.ident,
+            ty: item.ty,
+            expr: None,
+            abi: None,
+        }
+    }
+}
+
+impl From<ItemStatic> for Static {
+    fn from(item: ItemStatic) -> Self {
+        Static {
+            attrs: item.attrs,
+            vis: item.vis,
+            static_token: item.static_token,
+            mutability: item.mutability,
+            ident: item.ident,
+            ty: item.ty,
+            expr: Some(item.expr),
+            abi: None,
+        }
+    }
+}
+
+impl ToTokens for Static {
+    fn to_tokens(&self, tokens: &mut TokenStream2) {
+        tokens.append_all(&self.attrs);
+        self.vis.to_tokens(tokens);
+        self.static_token.to_tokens(tokens);
+        self.mutability.to_tokens(tokens);
+        self.ident.to_tokens(tokens);
+        <Token![:]>::default().to_tokens(tokens);
+        self.ty.to_tokens(tokens);
+        if let Some(expr) = self.expr.as_ref() {
+            <Token![=]>::default().to_tokens(tokens);
+            expr.to_tokens(tokens);
+        }
+        <Token![;]>::default().to_tokens(tokens);
+    }
+}
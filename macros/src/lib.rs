@@ -1,192 +1,238 @@
 extern crate proc_macro;
 extern crate proc_macro2;
 extern crate quote;
-extern crate rand;
 extern crate syn;
 
+mod args;
+mod check;
+mod expand;
+mod group;
+mod item;
+mod util;
+
 use proc_macro::TokenStream;
-use std::{
-    sync::atomic::{AtomicUsize, Ordering},
-    time::{SystemTime, UNIX_EPOCH},
-};
+use proc_macro2::TokenStream as TokenStream2;
+use syn::{parse, parse_macro_input, ForeignItemStatic, Item, ItemMod};
 
-use proc_macro2::Span;
-use quote::quote;
-use rand::{Rng, SeedableRng};
-use syn::{
-    parse::{self, Parse, ParseStream},
-    parse_macro_input,
-    punctuated::Punctuated,
-    Ident, ItemStatic, Token,
-};
+use args::Args;
 
 /// Attribute to declare an owned singleton
 ///
-/// This attribute must be applied to a `static [mut]` variable.
+/// This attribute must be applied to a `static [mut]` variable. The variable can be declared at
+/// the module level or inside a function body.
+///
+/// The attribute can also be applied to an `extern` block; each `static [mut]` variable declared
+/// in the block, whose storage is defined elsewhere (e.g. in C code or a linker script), gets its
+/// own proxy.
 ///
 /// The attribute accepts two arguments: `Send` and `Sync` (e.g. `#[Singleton(Send, Sync)]`)
 ///
+/// `Send(force)` and `Sync(force)` implement the trait even if the type of the `static` variable
+/// doesn't implement it; the user is responsible for making sure that's sound.
+///
+/// When the `take` feature is enabled the proxy also gets a `take` constructor that returns `Some`
+/// only the first time it's called. In that case the `release` argument can be used to make the
+/// singleton available to `take` again once the proxy is dropped. Proxies of `static mut` arrays
+/// also get a `take_nth` constructor that hands out the elements of the array one by one.
+///
+/// When the `const-ptr` feature is enabled the proxy also implements `ConstSingleton`, unless the
+/// `lazy` or `per_cpu` arguments are used.
+///
+/// When the `defmt`, `serde` or `ufmt` features are enabled the proxy also implements
+/// `defmt::Format`, `Serialize` or `uDebug` and `uDisplay`, respectively, if the type of the
+/// variable does.
+///
 /// The expansion will produce a proxy struct whose name matches the identifier of the `static`
-/// variable.
+/// variable. Doc comments are moved to the proxy struct; all other attributes stay on the
+/// `static` variable. Use the `name` argument to pick a different name for the proxy (e.g.
+/// `#[Singleton(name = "UartBuf")]`).
+///
+/// The `static` variable is renamed to an identifier generated from the crate name and version, the
+/// tokens of the item and their location in the source code. The `readable` argument
+/// makes the macro use the `__NAME` identifier instead and exports the variable under the
+/// `path::to::module::NAME` symbol (`readable` variables declared inside functions must have
+/// unique names within their module). The `no_export` argument omits the exported symbol altogether
+/// and hides the variable in an anonymous `const` item.
+///
+/// The value of a `static mut` variable is stored in an `UnsafeCell` inside a plain `static`. The
+/// proxy, `Singleton::get` and `Singleton::steal` derive all pointers and references from the
+/// cell, so no reference to the variable is created behind the proxy's back.
+///
+/// The `deny_duplicates` argument exports the variable under the `path::to::module::NAME` symbol
+/// and keeps it even if it's unused, so declaring the same singleton twice (e.g. by linking two
+/// versions of the same crate) results in a duplicate symbol error at link time.
+///
+/// The `export_name` argument exports the variable under the given symbol (e.g.
+/// `#[Singleton(export_name = "g_uart_state")]`) so it can be referenced from C code, assembly or
+/// debugger scripts. The `no_mangle` argument exports the variable under its own name.
+///
+/// The `ffi` argument generates an `extern "C" fn NAME_get() -> *mut T` function that C code can
+/// use to reach the variable. Fields listed in the argument (e.g. `ffi(baud_rate: u32)`)
+/// additionally get `NAME_get_field` and, for `static mut` variables, `NAME_set_field` functions.
+/// These functions bypass the ownership model; C code must not use them while the proxy is in use.
+///
+/// The `on_drop` argument names a function that's called with a mutable reference to the
+/// `static mut` variable when the proxy is dropped (e.g. `#[Singleton(on_drop = shutdown)]`). With
+/// `release` the function runs before the singleton is made available again.
+///
+/// The `zeroize` argument overwrites the `static mut` variable with zeros when the proxy is
+/// dropped; the all-zeros bit pattern must be a valid value of the variable's type. When the
+/// `zeroize` feature is enabled the proxy also implements `Zeroize`, if the type of the variable
+/// does, and `ZeroizeOnDrop`, if the argument is used.
+///
+/// The `volatile` argument is meant for variables shared with DMA engines or debuggers. The proxy
+/// doesn't implement `Deref`, `DerefMut` or `Singleton`; instead it gets `read`, `write` and
+/// `modify` methods that use volatile operations. The type of the variable must implement `Copy`.
+///
+/// The `per_cpu` argument creates one copy of the variable per core; the proxy accesses the copy
+/// that belongs to the core returned by the `core` function (e.g.
+/// `#[Singleton(per_cpu = 2, core = current_core)]`, where `fn current_core() -> usize`). Each core
+/// owns its own proxy so `per_cpu` can't be used together with `Send` or `Sync`, and the proxy
+/// can't be turned into a `Shared` handle (`freeze`) or a `Token`.
+///
+/// The `pinned` argument is meant for self-referential or intrusive data structures. The proxy
+/// doesn't implement `DerefMut` or `Unpin`; instead it gets `as_pin_mut` and `unwrap_pinned`
+/// methods that return pinned references to the `static mut` variable.
+///
+/// The `refcount` argument implements `RefCounted` for the proxy so it can be turned into
+/// reference counted, read-only `Ref` handles. `Ref::upgrade` turns the last handle back into the
+/// proxy.
+///
+/// The `stack` argument is meant for the stacks of RTOS tasks or executors. The variable must be
+/// a `static mut` array of integers or of `MaybeUninit<T>`; it's aligned to 8 bytes, unless the
+/// `align` argument says otherwise, and the proxy gets `bottom`, `top` and `size` methods.
+/// `stack(paint = 0xa5)` additionally adds a `paint` method that fills the stack with the given
+/// byte, and a `high_water_mark` method that reports how many bytes have been used since.
+///
+/// The `section` argument places the `static` variable in the given linker section (e.g.
+/// `#[Singleton(section = ".dtcm")]`).
+///
+/// The `uninit` argument is meant for large `static mut` buffers of type `MaybeUninit<T>`. The
+/// buffer is placed in a `.uninit` linker section so it's neither stored in Flash nor zeroed at
+/// startup; `init_zeroed` and `write` methods are added to the proxy to initialize the buffer.
+///
+/// The `noinit` argument is meant for state that must survive a warm reset, like boot counters or
+/// crash information. The variable must be a `static mut` of type `MaybeUninit<T>` declared
+/// without an initializer; it's placed in a `.uninit` linker section, next to a header that holds
+/// a magic number. The proxy gets `write`, which stores a value and marks the variable as valid,
+/// `is_valid`, `valid`, `modify` and `invalidate` methods. `noinit(crc)` also stores a CRC-32 of
+/// the value in the header, so corrupted contents are detected as invalid.
+///
+/// The `init` argument is meant for values that can only be constructed with runtime data, like
+/// drivers that need the clock frequency. The `static mut` variable is declared without an
+/// initializer and the proxy is obtained with `FOO::init(value)`, which returns `value` back if the
+/// variable has already been initialized; `FOO::is_initialized` reports whether it has.
+/// `FOO::init_for_task(value)` is the `StaticCell::init` equivalent: it returns a `&'static mut`
+/// reference and panics if the variable has already been initialized. `init` is the checked
+/// constructor of these singletons so no `take` constructor is generated.
+///
+/// The `typestate` argument encodes late initialization in the type system. The variable must be
+/// a `static mut` of type `MaybeUninit<T>` and the proxy becomes generic over its state:
+/// `FOO<Uninit>`, created with `uninit` (or `take`), only has `init` and `init_with` methods,
+/// which return a `FOO<Init>` (or simply `FOO`); only the initialized proxy dereferences to `T`.
+///
+/// The `lazy` argument is meant for values that can't be computed at compile time. The `static`
+/// variable is declared without an initializer and the given function is called to initialize it
+/// the first time the proxy is dereferenced (e.g. `#[Singleton(lazy = make_table)]`). `lazy` can't
+/// be used together with `Sync`.
+///
+/// The `address` argument is meant for memory that lives at a fixed address, like shared memory
+/// mailboxes. The `static` variable is declared without an initializer and the proxy accesses the
+/// memory at the given address (e.g. `#[Singleton(address = 0x2000_c000)]`) instead of compiler
+/// managed storage.
+///
+/// The `align` argument raises the alignment of the `static` variable to the given power of two
+/// (e.g. `#[Singleton(align = 32)]`).
+///
+/// The `max_size` argument makes compilation fail if the size of the `static` variable exceeds
+/// the given number of bytes (e.g. `#[Singleton(max_size = 4096)]`).
+///
+/// The `count` argument declares a family of independent singletons: `count = N` produces N
+/// `static` variables and proxies whose names are suffixed with `0` to `N - 1` (e.g.
+/// `#[Singleton(count = 4)] static mut BUF` produces the proxies `BUF0` to `BUF3`).
+///
+/// The `split` argument lists fields of the `static` variable as `field: Type` pairs (e.g.
+/// `#[Singleton(split(rx: Rx, tx: Tx))]`). The expansion then contains one proxy per field, named
+/// `NAME_FIELD`, plus a `split` method that turns the proxy into a tuple of field proxies.
+///
+/// Proxies of `static` variables implement `Clone` and `Copy`, and have a safe `new` constructor,
+/// as they only hand out shared references, unless the `release` or `lazy` arguments are used.
+/// `Send` requires the type of such variables to implement `Sync`.
+///
+/// The `reset` argument gives the proxy of a `static mut` variable a `const fn initial()` that
+/// returns the initial value of the variable and a `reset` method that restores it. It's opt-in
+/// because the initializer must also be valid in the body of a `const fn`.
+///
+/// Proxies implement `AsRef` and `Borrow`, for the type of the variable, and, if they implement
+/// `DerefMut`, `AsMut` and `BorrowMut` as well. Likewise, proxies implement `Index` and `IndexMut`
+/// if the type of the variable does.
+///
+/// Proxies of `static mut` arrays also get a `split` method that turns the proxy into an array of
+/// `Chunk` handles, each owning a disjoint, fixed size part of the array (e.g.
+/// `BUF.split::<16, 4>()` splits a `[u8; 64]` variable into 4 chunks of 16 bytes).
+///
+/// The `Debug`, `Display`, `Eq`, `Hash`, `Ord`, `PartialEq` and `PartialOrd` arguments implement
+/// those traits for the proxy by forwarding to the implementation of the variable's type (e.g.
+/// `#[Singleton(Debug)]`).
+///
+/// The `derive` argument applies the given derive macros to the proxy struct (e.g.
+/// `#[Singleton(derive(Debug))]`). `Clone`, `Copy` and `Default` are rejected as they would let
+/// safe code create more than one instance of the proxy.
+///
+/// The attribute rejects `#[no_mangle]`, `#[export_name]` and `#[thread_local]` variables, as well
+/// as types that contain non-`'static` lifetimes.
 ///
 /// For more information read the crate level documentation of the `owned-singleton` crate.
 #[allow(non_snake_case)]
 #[proc_macro_attribute]
 pub fn Singleton(args: TokenStream, input: TokenStream) -> TokenStream {
-    let item = parse_macro_input!(input as ItemStatic);
+    let input = TokenStream2::from(input);
     let args = parse_macro_input!(args as Args);
 
-    if let Err(e) = check(&item) {
-        return e.to_compile_error().into();
-    }
-
-    let attrs = &item.attrs;
-    let vis = &item.vis;
-    let ident = &item.ident;
-    let ty = &item.ty;
-    let expr = &item.expr;
-    let alias = mk_ident();
-
-    let mut items = vec![];
-    let symbol = format!("{}::{}", ident, alias);
-    items.push(quote!(
-        #(#attrs)*
-        #[export_name = #symbol]
-        static mut #alias: #ty = #expr;
-
-        #vis struct #ident { #alias: owned_singleton::export::NotSendOrSync }
-
-        unsafe impl owned_singleton::Singleton for #ident {
-            type Type = #ty;
-
-            #[inline]
-            unsafe fn new() -> Self {
-                #ident { #alias: owned_singleton::export::PhantomData }
-            }
-
-            #[inline]
-            fn get() -> *mut Self::Type {
-                unsafe { &mut #alias }
-            }
-        }
-
-        impl owned_singleton::export::Deref for #ident {
-            type Target = #ty;
-
-            #[inline]
-            fn deref(&self) -> &Self::Target {
-                unsafe { &#alias }
-            }
-        }
-
-        unsafe impl owned_singleton::export::StableDeref for #ident {}
-    ));
-
-    if args.send {
-        items.push(quote!(
-            unsafe impl Send for #ident where #ty: Send {}
-        ));
-    }
-
-    if args.sync {
-        items.push(quote!(
-            unsafe impl Sync for #ident where #ty: Sync {}
-        ));
+    match syn::parse2(input.clone()) {
+        Ok(Item::Static(item)) => expand::expand(&args, &item.into()),
+        Ok(Item::ForeignMod(item)) => expand::expand_foreign(&args, &item),
+        Ok(item) => Err(parse::Error::new_spanned(
+            item,
+            "expected a `static` variable or an `extern` block",
+        )),
+        // `static` variables without an initializer (`lazy`)
+        Err(e) => syn::parse2::<ForeignItemStatic>(input)
+            .map_err(|_| e)
+            .and_then(|item| expand::expand(&args, &item.into())),
     }
-
-    if item.mutability.is_some() {
-        items.push(quote!(
-            impl owned_singleton::export::DerefMut for #ident {
-                #[inline]
-                fn deref_mut(&mut self) -> &mut Self::Target {
-                    unsafe { &mut #alias }
-                }
-            }
-        ));
-    }
-
-    quote!(#(#items)*).into()
-}
-
-struct Args {
-    send: bool,
-    sync: bool,
+    .unwrap_or_else(|e| e.to_compile_error())
+    .into()
 }
 
-impl Parse for Args {
-    fn parse(input: ParseStream) -> parse::Result<Self> {
-        let mut send = false;
-        let mut sync = false;
-        let punctuated = Punctuated::<Ident, Token![,]>::parse_terminated(input)?;
-
-        for ident in punctuated {
-            match &*ident.to_string() {
-                "Send" => {
-                    if send {
-                        return Err(parse::Error::new(ident.span(), "this trait appears twice"));
-                    }
-
-                    send = true;
-                }
-                "Sync" => {
-                    if sync {
-                        return Err(parse::Error::new(ident.span(), "this trait appears twice"));
-                    }
-
-                    sync = true;
-                }
-                _ => {
-                    return Err(parse::Error::new(
-                        ident.span(),
-                        "expected one of: Send or Sync",
-                    ))
-                }
-            }
-        }
-
-        Ok(Args { send, sync })
-    }
-}
-
-fn check(_item: &ItemStatic) -> parse::Result<()> {
-    // TODO
-
-    Ok(())
-}
-
-fn mk_ident() -> Ident {
-    static CALL_COUNT: AtomicUsize = AtomicUsize::new(0);
-
-    let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-
-    let secs = elapsed.as_secs();
-    let nanos = elapsed.subsec_nanos();
-
-    let count = CALL_COUNT.fetch_add(1, Ordering::SeqCst) as u32;
-    let mut seed: [u8; 16] = [0; 16];
-
-    for (i, v) in seed.iter_mut().take(8).enumerate() {
-        *v = ((secs >> (i * 8)) & 0xFF) as u8
-    }
-
-    for (i, v) in seed.iter_mut().skip(8).take(4).enumerate() {
-        *v = ((nanos >> (i * 8)) & 0xFF) as u8
-    }
-
-    for (i, v) in seed.iter_mut().skip(12).enumerate() {
-        *v = ((count >> (i * 8)) & 0xFF) as u8
+/// Attribute to declare a group of owned singletons
+///
+/// This attribute must be applied to an inline `mod` item. Every `static [mut]` variable in the
+/// module is turned into an owned singleton, as if it had been annotated with `#[Singleton]`;
+/// per-variable arguments can be passed with a `#[Singleton(..)]` attribute on the variable. Other
+/// items are left untouched.
+///
+/// The expansion also adds a `Resources` struct to the module with one field per singleton; the
+/// name of each field is the name of the proxy in lowercase. `Resources` has an
+/// `unsafe fn new` constructor and, when the `take` feature is enabled, a `take` constructor that
+/// returns `Some` only if none of the singletons has been taken yet.
+///
+/// For more information read the crate level documentation of the `owned-singleton` crate.
+#[allow(non_snake_case)]
+#[proc_macro_attribute]
+pub fn Singletons(args: TokenStream, input: TokenStream) -> TokenStream {
+    let item = parse_macro_input!(input as ItemMod);
+
+    if !args.is_empty() {
+        return parse::Error::new(
+            TokenStream2::from(args).into_iter().next().unwrap().span(),
+            "this attribute takes no arguments",
+        )
+        .to_compile_error()
+        .into();
     }
 
-    let mut rng = rand::rngs::SmallRng::from_seed(seed);
-    Ident::new(
-        &(0..16)
-            .map(|i| {
-                if i == 0 || rng.gen() {
-                    ('a' as u8 + rng.gen::<u8>() % 25) as char
-                } else {
-                    ('0' as u8 + rng.gen::<u8>() % 10) as char
-                }
-            }).collect::<String>(),
-        Span::call_site(),
-    )
+    group::expand_mod(&item)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
 }
@@ -0,0 +1,108 @@
+use std::env;
+
+use proc_macro2::{Span, TokenStream};
+use syn::{Expr, GenericArgument, Ident, PathArguments, Type};
+
+/// Returns `T` if `ty` is `[T; N]`
+pub fn array(ty: &Type) -> Option<&Type> {
+    match ty {
+        Type::Array(ty) => Some(&ty.elem),
+        Type::Group(ty) => array(&ty.elem),
+        Type::Paren(ty) => array(&ty.elem),
+        _ => None,
+    }
+}
+
+/// Returns `N` if `ty` is `[T; N]`
+pub fn array_len(ty: &Type) -> Option<&Expr> {
+    match ty {
+        Type::Array(ty) => Some(&ty.len),
+        Type::Group(ty) => array_len(&ty.elem),
+        Type::Paren(ty) => array_len(&ty.elem),
+        _ => None,
+    }
+}
+
+/// Returns `T` if `ty` is `MaybeUninit<T>`
+pub fn maybe_uninit(ty: &Type) -> Option<&Type> {
+    let path = match ty {
+        Type::Path(ty) if ty.qself.is_none() => &ty.path,
+        _ => return None,
+    };
+
+    let segment = path.segments.iter().last()?;
+    if segment.ident != "MaybeUninit" {
+        return None;
+    }
+
+    match &segment.arguments {
+        PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// Returns the seed from which the hidden identifiers of an item are generated
+///
+/// The seed is made of the tokens of the item and their location in the source code of the crate
+/// (the byte ranges reported by their spans). Identical items declared in different places (e.g.
+/// in two modules or in two functions) get different seeds.
+pub fn seed(tokens: &TokenStream) -> String {
+    let mut seed = tokens.to_string();
+
+    // NOTE the `Debug` representation is the only way to observe the location of a span on stable;
+    // only the byte ranges are used as the syntax contexts (`#N`) depend on the expansion order
+    let debug = format!("{:?}", tokens);
+    let mut rest = &*debug;
+    while let Some(start) = rest.find("bytes(") {
+        rest = &rest[start..];
+        let end = rest.find(')').map_or(rest.len(), |end| end + 1);
+        seed.push_str(&rest[..end]);
+        rest = &rest[end..];
+    }
+
+    seed
+}
+
+/// Generates an identifier from the given `seed` and `label`
+///
+/// The identifier is the 128-bit FNV-1a hash of the name and version of the crate being compiled,
+/// the `seed` (see [`seed`]) and the `label`, which tells apart the identifiers generated for the
+/// same item. The same source code always produces the same identifiers, regardless of the order
+/// in which the items are expanded or the version of the compiler.
+pub fn mk_ident(seed: &str, label: &str) -> Ident {
+    const OFFSET_BASIS: u128 = 0x6c62_272e_07bb_0142_62b8_2175_6295_c58d;
+    const PRIME: u128 = 0x0000_0000_0100_0000_0000_0000_0000_013b;
+
+    let krate = env::var("CARGO_CRATE_NAME").unwrap_or_default();
+    let version = env::var("CARGO_PKG_VERSION").unwrap_or_default();
+
+    let mut hash = OFFSET_BASIS;
+    // NOTE the 0xff separators keep e.g. ("ab", "c") and ("a", "bc") apart; it's not valid UTF-8
+    for part in &[&*krate, &*version, seed, label] {
+        for byte in part.bytes().chain(Some(0xff)) {
+            hash ^= u128::from(byte);
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+
+    Ident::new(
+        &(0..16)
+            .map(|i| {
+                // the first character must be a letter
+                let base = if i == 0 { 26 } else { 36 };
+                let digit = (hash % base) as u8;
+                hash /= base;
+
+                if digit < 26 {
+                    (b'a' + digit) as char
+                } else {
+                    (b'0' + digit - 26) as char
+                }
+            })
+            .collect::<String>(),
+        Span::call_site(),
+    )
+}
@@ -0,0 +1,323 @@
+//! Allocators backed by singletons
+//!
+//! [`Bump`](struct.Bump.html) carves allocations out of a `[u8; N]` variable. Allocating only
+//! moves a cursor forward; all the allocations are freed at once by `reset`, which needs exclusive
+//! access to the allocator so no reference to the memory it hands out can outlive the reset.
+//!
+//! [`Heap`](struct.Heap.html) is a `GlobalAlloc`ator over a `[u8; N]` variable, so `no_std`
+//! programs can get `alloc` support from a singleton.
+//!
+//! With the `allocator-api` feature, which requires a nightly compiler, `&Bump` implements
+//! `core::alloc::Allocator` so collections can allocate from it (e.g. `Vec<T, &Bump<S>>`).
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    cell::Cell,
+    cmp, fmt,
+    marker::PhantomData,
+    ptr,
+    ptr::NonNull,
+    slice,
+};
+
+#[cfg(feature = "allocator-api")]
+use core::alloc::{AllocError, Allocator};
+
+use atomic::{AtomicUsize, Ordering};
+use {export, SingletonMut};
+
+/// A bump allocator over the `[u8; N]` variable behind the singleton `S`
+///
+/// Destructors of the values allocated by `Bump` are not run.
+pub struct Bump<S> {
+    singleton: S,
+    cursor: Cell<usize>,
+}
+
+impl<S, const N: usize> Bump<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    /// Wraps the proxy; the whole buffer is available
+    pub fn new(singleton: S) -> Self {
+        Bump {
+            singleton,
+            cursor: Cell::new(0),
+        }
+    }
+
+    /// Moves `value` into the buffer; returns it back if there's not enough space left
+    // NOTE each call hands out a different part of the buffer
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc<T>(&self, value: T) -> Result<&mut T, T> {
+        match self.alloc_layout(Layout::new::<T>()) {
+            Some(ptr) => unsafe {
+                let ptr = ptr.as_ptr() as *mut T;
+                ptr.write(value);
+                Ok(&mut *ptr)
+            },
+            None => Err(value),
+        }
+    }
+
+    /// Copies `slice` into the buffer, if there's enough space left
+    #[allow(clippy::mut_from_ref)]
+    pub fn alloc_slice_copy<T>(&self, slice: &[T]) -> Option<&mut [T]>
+    where
+        T: Copy,
+    {
+        let layout = Layout::array::<T>(slice.len()).ok()?;
+        let ptr = self.alloc_layout(layout)?.as_ptr() as *mut T;
+
+        unsafe {
+            ptr.copy_from_nonoverlapping(slice.as_ptr(), slice.len());
+            Some(slice::from_raw_parts_mut(ptr, slice.len()))
+        }
+    }
+
+    /// Reserves a block of memory that fits `layout`, if there's enough space left
+    ///
+    /// The block is not initialized.
+    pub fn alloc_layout(&self, layout: Layout) -> Option<NonNull<u8>> {
+        let start = S::get() as *mut u8;
+        let cursor = self.cursor.get();
+
+        // NOTE the buffer itself is only byte aligned so the padding depends on its address
+        let address = start as usize + cursor;
+        let padding = address.wrapping_neg() & (layout.align() - 1);
+        let offset = cursor.checked_add(padding)?;
+        let end = offset.checked_add(layout.size())?;
+
+        if end > N {
+            return None;
+        }
+
+        self.cursor.set(end);
+        NonNull::new(unsafe { start.add(offset) })
+    }
+
+    /// Frees all the allocations
+    ///
+    /// The mutable borrow guarantees that no reference to the freed memory is still alive.
+    pub fn reset(&mut self) {
+        self.cursor.set(0);
+    }
+
+    /// Returns the number of bytes in use, including alignment padding
+    pub fn used(&self) -> usize {
+        self.cursor.get()
+    }
+
+    /// Returns the number of bytes that haven't been allocated yet
+    pub fn remaining(&self) -> usize {
+        N - self.cursor.get()
+    }
+
+    /// Returns the size of the buffer
+    pub fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Releases the proxy
+    pub fn into_inner(self) -> S {
+        self.singleton
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+unsafe impl<S, const N: usize> Allocator for &Bump<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let ptr = self.alloc_layout(layout).ok_or(AllocError)?;
+        Ok(NonNull::slice_from_raw_parts(ptr, layout.size()))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
+        let offset = ptr.as_ptr() as usize - S::get() as *mut u8 as usize;
+
+        // only the most recent allocation can be reclaimed
+        if self.cursor.get() == offset + layout.size() {
+            self.cursor.set(offset);
+        }
+    }
+}
+
+impl<S> fmt::Debug for Bump<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Bump")
+            .field("used", &self.cursor.get())
+            .finish()
+    }
+}
+
+/// The value of `Heap.cursor` until `Heap::init` is called
+const UNINIT: usize = !0;
+
+/// A global allocator over the `[u8; N]` variable behind the singleton `S`
+///
+/// The heap hands out memory by moving a cursor forward. Freed memory is reclaimed only if it's
+/// the most recent allocation; likewise, only the most recent allocation can grow in place. This
+/// suits programs that allocate mostly at startup, or in a stack-like fashion.
+///
+/// All allocations fail until `init` hands the proxy over to the heap.
+pub struct Heap<S> {
+    cursor: AtomicUsize,
+    _singleton: PhantomData<S>,
+}
+
+impl<S> Heap<S> {
+    /// Creates an uninitialized heap
+    pub const fn new() -> Self {
+        Heap {
+            cursor: AtomicUsize::new(UNINIT),
+            _singleton: PhantomData,
+        }
+    }
+
+    /// Returns the number of bytes in use, including alignment padding
+    pub fn used(&self) -> usize {
+        match self.cursor.load(Ordering::Relaxed) {
+            UNINIT => 0,
+            cursor => cursor,
+        }
+    }
+
+    /// Moves the cursor to `f(cursor)` unless `f` returns `None`; returns the old cursor
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    fn update<F>(&self, f: F) -> Option<usize>
+    where
+        F: FnMut(usize) -> Option<usize>,
+    {
+        self.cursor
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, f)
+            .ok()
+    }
+
+    /// Moves the cursor to `f(cursor)` unless `f` returns `None`; returns the old cursor
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    fn update<F>(&self, mut f: F) -> Option<usize>
+    where
+        F: FnMut(usize) -> Option<usize>,
+    {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            let cursor = self.cursor.load(Ordering::Relaxed);
+            let new = f(cursor)?;
+            self.cursor.store(new, Ordering::Relaxed);
+            Some(cursor)
+        })
+    }
+}
+
+impl<S, const N: usize> Heap<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    /// Hands the proxy over to the heap; allocations can succeed from now on
+    pub fn init(&self, singleton: S) {
+        export::adopt(singleton);
+
+        self.cursor.store(0, Ordering::Release);
+    }
+
+    /// Returns the number of bytes that haven't been allocated yet
+    pub fn remaining(&self) -> usize {
+        N - self.used()
+    }
+
+    /// Returns the address of the buffer
+    fn start() -> *mut u8 {
+        S::get() as *mut u8
+    }
+
+    /// Returns the offset of the first byte after `cursor` that's aligned to `align`
+    fn align_up(cursor: usize, align: usize) -> Option<usize> {
+        // NOTE the buffer itself is only byte aligned so the padding depends on its address
+        let padding = (Self::start() as usize + cursor).wrapping_neg() & (align - 1);
+        cursor.checked_add(padding)
+    }
+}
+
+unsafe impl<S, const N: usize> GlobalAlloc for Heap<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let end = |cursor| {
+            Self::align_up(cursor, layout.align())?
+                .checked_add(layout.size())
+                .filter(|end| *end <= N)
+        };
+
+        match self.update(|cursor| if cursor == UNINIT { None } else { end(cursor) }) {
+            Some(cursor) => {
+                let offset = end(cursor).unwrap() - layout.size();
+                Self::start().add(offset)
+            }
+            None => ptr::null_mut(),
+        }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        let offset = ptr as usize - Self::start() as usize;
+
+        // only the most recent allocation can be reclaimed
+        self.update(|cursor| {
+            if cursor == offset + layout.size() {
+                Some(offset)
+            } else {
+                None
+            }
+        });
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let offset = ptr as usize - Self::start() as usize;
+
+        // the most recent allocation can grow, or shrink, in place
+        let in_place = self
+            .update(|cursor| {
+                if cursor == offset + layout.size() {
+                    offset.checked_add(new_size).filter(|end| *end <= N)
+                } else {
+                    None
+                }
+            })
+            .is_some();
+
+        if in_place {
+            return ptr;
+        }
+
+        let new_layout = Layout::from_size_align_unchecked(new_size, layout.align());
+        let new_ptr = self.alloc(new_layout);
+        if !new_ptr.is_null() {
+            ptr::copy_nonoverlapping(ptr, new_ptr, cmp::min(layout.size(), new_size));
+            self.dealloc(ptr, layout);
+        }
+        new_ptr
+    }
+}
+
+impl<S> Default for Heap<S> {
+    fn default() -> Self {
+        Heap::new()
+    }
+}
+
+impl<S> fmt::Debug for Heap<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Heap").field("used", &self.used()).finish()
+    }
+}
+
+// the cursor is only ever updated atomically
+unsafe impl<S> Sync for Heap<S> {}
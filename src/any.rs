@@ -0,0 +1,46 @@
+//! Object safe singletons
+
+use Singleton;
+
+/// Object safe version of the `Singleton` trait, implemented for all singletons
+///
+/// `Box<dyn AnySingleton>`, `&dyn AnySingleton` and the like can be used to store proxies of
+/// different types in the same collection.
+pub trait AnySingleton {
+    /// Returns the name of this proxy
+    fn name(&self) -> &'static str;
+
+    /// Returns a type-erased pointer to the variable behind this proxy
+    fn address(&self) -> *mut ();
+
+    /// Returns the size, in bytes, of the variable behind this proxy
+    fn size(&self) -> usize;
+
+    /// Returns the alignment, in bytes, of the variable behind this proxy
+    fn align(&self) -> usize;
+}
+
+impl<S> AnySingleton for S
+where
+    S: Singleton,
+{
+    #[inline]
+    fn name(&self) -> &'static str {
+        S::NAME
+    }
+
+    #[inline]
+    fn address(&self) -> *mut () {
+        S::get() as *mut ()
+    }
+
+    #[inline]
+    fn size(&self) -> usize {
+        S::SIZE
+    }
+
+    #[inline]
+    fn align(&self) -> usize {
+        S::ALIGN
+    }
+}
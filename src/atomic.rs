@@ -0,0 +1,29 @@
+//! Atomic types used by the runtime flags and counters
+
+#[cfg(all(feature = "track-owner", not(feature = "portable-atomic")))]
+pub use core::sync::atomic::AtomicPtr;
+#[cfg(not(feature = "portable-atomic"))]
+pub use core::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+#[cfg(all(feature = "track-owner", feature = "portable-atomic"))]
+pub use portable_atomic::AtomicPtr;
+#[cfg(feature = "portable-atomic")]
+pub use portable_atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+
+/// The atomics of the `take` flags and of the reference counts
+///
+/// When the crate's own unit tests are built with `cfg(loom)` these are `loom`'s atomics so that
+/// the take / release and reference counting logic is model checked. Other builds, including
+/// downstream crates built with `cfg(loom)`, always get the regular atomics, which can be created
+/// in `static` initializers.
+pub mod checked {
+    #[cfg(all(feature = "take", not(all(loom, test))))]
+    pub use super::AtomicBool;
+    #[cfg(not(all(loom, test)))]
+    pub use super::AtomicUsize;
+
+    #[cfg(all(feature = "take", loom, test))]
+    pub use loom::sync::atomic::AtomicBool;
+    #[cfg(all(loom, test))]
+    pub use loom::sync::atomic::AtomicUsize;
+}
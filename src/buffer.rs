@@ -0,0 +1,326 @@
+//! Buffers shared with hardware
+//!
+//! [`PingPong`](struct.PingPong.html) splits a `[[T; N]; 2]` variable into two halves for double
+//! buffered DMA: the CPU processes one half while the hardware fills, or drains, the other.
+//!
+//! [`Chunk`](struct.Chunk.html)s split a `[T; N]` variable into disjoint, fixed size parts, e.g.
+//! one per task or peripheral.
+//!
+//! With the `take` feature, an [`Element`](struct.Element.html) owns a single element of a
+//! `[T; N]` variable; elements are taken one by one with the `take_nth` method of the proxy.
+
+use core::{
+    array, fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "take")]
+use export::Elements;
+use {export, SingletonMut, StableDeref};
+
+/// The half of a `[[T; N]; 2]` variable that the CPU is working on
+///
+/// The other half is lent to the hardware as a [`Half`](struct.Half.html).
+pub struct PingPong<S> {
+    cpu: u8,
+    _singleton: PhantomData<S>,
+}
+
+impl<S, T, const N: usize> PingPong<S>
+where
+    S: SingletonMut<Type = [[T; N]; 2]>,
+{
+    /// Splits the variable behind the proxy into two halves
+    ///
+    /// The CPU starts working on the first half; the second half is returned as a `Half`, ready to
+    /// be handed to the hardware.
+    pub fn new(singleton: S) -> (Self, Half<S>) {
+        export::adopt(singleton);
+
+        (
+            PingPong {
+                cpu: 0,
+                _singleton: PhantomData,
+            },
+            Half {
+                index: 1,
+                _singleton: PhantomData,
+            },
+        )
+    }
+
+    /// Takes back the half the hardware is done with and lends it the half the CPU was working on
+    ///
+    /// From now on the CPU works on `done`.
+    pub fn swap(&mut self, done: Half<S>) -> Half<S> {
+        let next = Half {
+            index: self.cpu,
+            _singleton: PhantomData,
+        };
+        self.cpu = done.index;
+        next
+    }
+
+    /// Returns the index, `0` or `1`, of the half the CPU is working on
+    pub fn index(&self) -> usize {
+        usize::from(self.cpu)
+    }
+}
+
+impl<S, T, const N: usize> Deref for PingPong<S>
+where
+    S: SingletonMut<Type = [[T; N]; 2]>,
+{
+    type Target = [T; N];
+
+    #[inline]
+    fn deref(&self) -> &[T; N] {
+        unsafe { &(*S::get())[usize::from(self.cpu)] }
+    }
+}
+
+impl<S, T, const N: usize> DerefMut for PingPong<S>
+where
+    S: SingletonMut<Type = [[T; N]; 2]>,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T; N] {
+        // NOTE the hardware only has access to the other half
+        unsafe { &mut (*S::get())[usize::from(self.cpu)] }
+    }
+}
+
+unsafe impl<S, T, const N: usize> StableDeref for PingPong<S> where
+    S: SingletonMut<Type = [[T; N]; 2]>
+{
+}
+
+impl<S> fmt::Debug for PingPong<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PingPong").field("cpu", &self.cpu).finish()
+    }
+}
+
+/// The half of a `[[T; N]; 2]` variable that's lent to the hardware
+///
+/// `Half` points into a `static` variable and implements `StableDeref` so it can be handed to DMA
+/// APIs that require `'static` buffers (e.g. through `embedded_dma::WriteBuffer`).
+pub struct Half<S> {
+    index: u8,
+    _singleton: PhantomData<S>,
+}
+
+impl<S> Half<S> {
+    /// Returns the index, `0` or `1`, of this half
+    pub fn index(&self) -> usize {
+        usize::from(self.index)
+    }
+}
+
+impl<S, T, const N: usize> Deref for Half<S>
+where
+    S: SingletonMut<Type = [[T; N]; 2]>,
+{
+    type Target = [T; N];
+
+    #[inline]
+    fn deref(&self) -> &[T; N] {
+        unsafe { &(*S::get())[usize::from(self.index)] }
+    }
+}
+
+impl<S, T, const N: usize> DerefMut for Half<S>
+where
+    S: SingletonMut<Type = [[T; N]; 2]>,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T; N] {
+        // NOTE the CPU only has access to the other half
+        unsafe { &mut (*S::get())[usize::from(self.index)] }
+    }
+}
+
+unsafe impl<S, T, const N: usize> StableDeref for Half<S> where S: SingletonMut<Type = [[T; N]; 2]> {}
+
+impl<S> fmt::Debug for Half<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Half").field("index", &self.index).finish()
+    }
+}
+
+/// A `CHUNK` elements long part of the `[T; N]` variable behind the singleton `S`
+///
+/// Created by `Chunk::split`, or by the `split` method of the proxy. Each chunk owns a disjoint
+/// part of the array so chunks can be handed to different tasks or peripherals.
+pub struct Chunk<S, const CHUNK: usize> {
+    index: usize,
+    _singleton: PhantomData<S>,
+}
+
+impl<S, T, const N: usize, const CHUNK: usize> Chunk<S, CHUNK>
+where
+    S: SingletonMut<Type = [T; N]>,
+{
+    /// Splits the array behind the proxy into `M` chunks
+    ///
+    /// `M` chunks of `CHUNK` elements must cover the whole array; otherwise compilation fails.
+    pub fn split<const M: usize>(singleton: S) -> [Self; M] {
+        #[allow(clippy::let_unit_value)]
+        let () = Cover::<N, CHUNK, M>::CHECK;
+
+        export::adopt(singleton);
+
+        array::from_fn(|index| Chunk {
+            index,
+            _singleton: PhantomData,
+        })
+    }
+
+    /// Returns the position of this chunk in the array, counted in chunks
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns a pointer to the first element of this chunk
+    fn as_ptr(&self) -> *mut [T; CHUNK] {
+        unsafe { (S::get() as *mut T).add(self.index * CHUNK) as *mut [T; CHUNK] }
+    }
+}
+
+impl<S, T, const N: usize, const CHUNK: usize> Deref for Chunk<S, CHUNK>
+where
+    S: SingletonMut<Type = [T; N]>,
+{
+    type Target = [T; CHUNK];
+
+    #[inline]
+    fn deref(&self) -> &[T; CHUNK] {
+        unsafe { &*self.as_ptr() }
+    }
+}
+
+impl<S, T, const N: usize, const CHUNK: usize> DerefMut for Chunk<S, CHUNK>
+where
+    S: SingletonMut<Type = [T; N]>,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [T; CHUNK] {
+        // NOTE no other chunk overlaps this one
+        unsafe { &mut *self.as_ptr() }
+    }
+}
+
+unsafe impl<S, T, const N: usize, const CHUNK: usize> StableDeref for Chunk<S, CHUNK> where
+    S: SingletonMut<Type = [T; N]>
+{
+}
+
+impl<S, const CHUNK: usize> fmt::Debug for Chunk<S, CHUNK> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Chunk").field("index", &self.index).finish()
+    }
+}
+
+/// Rejects chunk layouts that don't cover the `[T; N]` array exactly
+struct Cover<const N: usize, const CHUNK: usize, const M: usize>;
+
+impl<const N: usize, const CHUNK: usize, const M: usize> Cover<N, CHUNK, M> {
+    const CHECK: () = assert!(
+        CHUNK * M == N,
+        "`M` chunks of `CHUNK` elements must cover the whole array"
+    );
+}
+
+/// An element of the `[T; N]` variable behind the singleton `S`
+///
+/// Created by `Element::take`, or by the `take_nth` method of the proxy.
+#[cfg(feature = "take")]
+pub struct Element<S>
+where
+    S: Elements,
+{
+    index: usize,
+    _singleton: PhantomData<S>,
+}
+
+#[cfg(feature = "take")]
+impl<S> Element<S>
+where
+    S: Elements,
+{
+    /// Takes the element at `index`
+    ///
+    /// Returns `None` if `index` is out of bounds, or if the element, or the whole array, has
+    /// already been taken
+    pub fn take(index: usize) -> Option<Self> {
+        if S::flags().get(index)?.acquire() {
+            Some(Element {
+                index,
+                _singleton: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the position of this element in the array
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+#[cfg(feature = "take")]
+impl<S, T, const N: usize> Deref for Element<S>
+where
+    S: Elements + SingletonMut<Type = [T; N]>,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { &*(S::get() as *mut T).add(self.index) }
+    }
+}
+
+#[cfg(feature = "take")]
+impl<S, T, const N: usize> DerefMut for Element<S>
+where
+    S: Elements + SingletonMut<Type = [T; N]>,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        // NOTE the flag of the element guarantees that there's no other handle to it
+        unsafe { &mut *(S::get() as *mut T).add(self.index) }
+    }
+}
+
+#[cfg(feature = "take")]
+unsafe impl<S, T, const N: usize> StableDeref for Element<S> where
+    S: Elements + SingletonMut<Type = [T; N]>
+{
+}
+
+#[cfg(feature = "take")]
+impl<S> Drop for Element<S>
+where
+    S: Elements,
+{
+    fn drop(&mut self) {
+        if S::RELEASE {
+            S::flags()[self.index].release();
+        }
+    }
+}
+
+#[cfg(feature = "take")]
+impl<S> fmt::Debug for Element<S>
+where
+    S: Elements,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Element")
+            .field("index", &self.index)
+            .finish()
+    }
+}
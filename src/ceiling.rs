@@ -0,0 +1,204 @@
+//! Sharing singletons between contexts with a priority ceiling
+//!
+//! [`Ceiling<S, PRIORITY>`](struct.Ceiling.html) keeps the proxy `S` and can be placed in a
+//! `static` that's shared between thread code and interrupt handlers. `lock` masks the
+//! interrupts whose priority is `PRIORITY` or lower around the closure, as the Stack Resource
+//! Policy does, so contexts whose priority doesn't exceed the ceiling never contend for the
+//! variable. Interrupts above the ceiling keep running.
+//!
+//! How interrupts are masked depends on the target: e.g. ARMv7-M raises `BASEPRI`. The
+//! application provides the implementation with the
+//! [`set_ceiling_impl!`](../macro.set_ceiling_impl.html) macro.
+//!
+//! ```
+//! use owned_singleton::ceiling::{self, Ceiling};
+//! use owned_singleton::{set_ceiling_impl, Singleton};
+//!
+//! struct Basepri;
+//!
+//! unsafe impl ceiling::Impl for Basepri {
+//!     unsafe fn raise(priority: u8) -> u8 {
+//!         // e.g. read `BASEPRI`, then write `priority` into `BASEPRI_MAX`
+//!         # let _ = priority;
+//!         0
+//!     }
+//!
+//!     unsafe fn restore(state: u8) {
+//!         // e.g. write `state` back into `BASEPRI`
+//!         # let _ = state;
+//!     }
+//! }
+//!
+//! set_ceiling_impl!(Basepri);
+//!
+//! #[Singleton]
+//! static mut COUNTER: u32 = 0;
+//!
+//! // shared between `main` and the `TIMER` interrupt handler, which runs at priority 2
+//! static SHARED: Ceiling<COUNTER, 2> = unsafe { Ceiling::new_unchecked() };
+//!
+//! #[allow(non_snake_case)]
+//! fn TIMER() {
+//!     SHARED.lock(|counter| *counter += 1);
+//! }
+//!
+//! TIMER();
+//! let count = SHARED.lock(|counter| *counter);
+//! assert_eq!(count, 1);
+//! ```
+
+use core::marker::PhantomData;
+
+use atomic::{AtomicU8, Ordering};
+use {export, SingletonMut};
+
+/// The variable is not locked
+const FREE: u8 = 0;
+/// The variable is locked
+const LOCKED: u8 = 1;
+
+/// Masks interrupts up to a priority ceiling
+///
+/// # Safety
+///
+/// Until `restore` is called, `raise` must prevent all interrupts of priority `priority` or
+/// lower from running
+pub unsafe trait Impl {
+    /// Masks the interrupts of priority `priority` or lower; returns the state to restore
+    ///
+    /// # Safety
+    ///
+    /// Each call must be paired with a call to `restore`; pairs must be properly nested
+    unsafe fn raise(priority: u8) -> u8;
+
+    /// Restores the state returned by the matching `raise`
+    ///
+    /// # Safety
+    ///
+    /// `state` must be the value returned by the innermost `raise` that hasn't been restored yet
+    unsafe fn restore(state: u8);
+}
+
+extern "Rust" {
+    fn _owned_singleton_ceiling_raise(priority: u8) -> u8;
+    fn _owned_singleton_ceiling_restore(state: u8);
+}
+
+/// Restores the interrupt mask when dropped, also when the closure unwinds
+struct Restore {
+    state: u8,
+}
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        unsafe { _owned_singleton_ceiling_restore(self.state) }
+    }
+}
+
+/// Runs `f` with the interrupts of priority `PRIORITY` or lower masked
+pub fn with_ceiling<const PRIORITY: u8, R, F>(f: F) -> R
+where
+    F: FnOnce() -> R,
+{
+    let _restore = Restore {
+        state: unsafe { _owned_singleton_ceiling_raise(PRIORITY) },
+    };
+
+    f()
+}
+
+/// The singleton `S` shared by the contexts whose priority is `PRIORITY` or lower
+pub struct Ceiling<S, const PRIORITY: u8> {
+    state: AtomicU8,
+    _singleton: PhantomData<S>,
+}
+
+impl<S, const PRIORITY: u8> Ceiling<S, PRIORITY>
+where
+    S: SingletonMut,
+{
+    /// Takes over the proxy
+    pub fn new(singleton: S) -> Self {
+        export::adopt(singleton);
+
+        unsafe { Self::new_unchecked() }
+    }
+
+    /// Creates the ceiling without a proxy; this can be used to initialize a `static`
+    ///
+    /// # Safety
+    ///
+    /// The proxy of `S` must not be used while the ceiling exists, and only one ceiling may exist
+    /// for each singleton
+    pub const unsafe fn new_unchecked() -> Self {
+        Ceiling {
+            state: AtomicU8::new(FREE),
+            _singleton: PhantomData,
+        }
+    }
+
+    /// Runs `f` on the variable with the interrupts of priority `PRIORITY` or lower masked
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the variable is already locked, i.e. if it's called from `f` or from
+    /// an interrupt handler whose priority is above the ceiling
+    pub fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut S::Type) -> R,
+    {
+        with_ceiling::<PRIORITY, R, _>(|| {
+            if !self.acquire() {
+                panic!("the variable is already locked");
+            }
+
+            let _release = Release(&self.state);
+            f(unsafe { &mut *S::get() })
+        })
+    }
+
+    /// Marks the variable as locked; returns `false` if it already was
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    fn acquire(&self) -> bool {
+        self.state.swap(LOCKED, Ordering::Acquire) == FREE
+    }
+
+    /// Marks the variable as locked; returns `false` if it already was
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    fn acquire(&self) -> bool {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            if self.state.load(Ordering::Relaxed) == FREE {
+                self.state.store(LOCKED, Ordering::Relaxed);
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+/// Marks the variable as not locked when dropped
+struct Release<'a>(&'a AtomicU8);
+
+impl<'a> Drop for Release<'a> {
+    fn drop(&mut self) {
+        self.0.store(FREE, Ordering::Release)
+    }
+}
+
+// NOTE `lock` hands out at most one `&mut` reference at a time
+unsafe impl<S, const PRIORITY: u8> Sync for Ceiling<S, PRIORITY>
+where
+    S: SingletonMut,
+    S::Type: Send,
+{
+}
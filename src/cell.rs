@@ -0,0 +1,163 @@
+//! Runtime checked borrows
+//!
+//! [`BorrowSingleton`](struct.BorrowSingleton.html) keeps the proxy and hands out shared or
+//! exclusive borrows of the variable behind it, `RefCell` style, so the variable can be reached
+//! from several call sites without threading `&mut` references through them.
+
+use core::{
+    cell::Cell,
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+use SingletonMut;
+
+/// The value of `BorrowSingleton.borrows` while the variable is mutably borrowed; any other value
+/// is the number of shared borrows
+const WRITING: usize = !0;
+
+/// The singleton `S` with dynamically checked borrow rules
+pub struct BorrowSingleton<S> {
+    singleton: S,
+    borrows: Cell<usize>,
+}
+
+impl<S> BorrowSingleton<S>
+where
+    S: SingletonMut,
+{
+    /// Wraps the proxy
+    pub fn new(singleton: S) -> Self {
+        BorrowSingleton {
+            singleton,
+            borrows: Cell::new(0),
+        }
+    }
+
+    /// Immutably borrows the variable behind the proxy
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the variable is currently mutably borrowed
+    pub fn borrow<'a>(&'a self) -> BorrowRef<'a, S> {
+        self.try_borrow()
+            .expect("the singleton is already mutably borrowed")
+    }
+
+    /// Immutably borrows the variable behind the proxy, unless it's currently mutably borrowed
+    pub fn try_borrow<'a>(&'a self) -> Option<BorrowRef<'a, S>> {
+        let borrows = self.borrows.get();
+        // NOTE `WRITING - 1` borrows would make the count indistinguishable from `WRITING`
+        if borrows >= WRITING - 1 {
+            None
+        } else {
+            self.borrows.set(borrows + 1);
+            Some(BorrowRef { cell: self })
+        }
+    }
+
+    /// Mutably borrows the variable behind the proxy
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the variable is currently borrowed
+    pub fn borrow_mut<'a>(&'a self) -> BorrowRefMut<'a, S> {
+        self.try_borrow_mut()
+            .expect("the singleton is already borrowed")
+    }
+
+    /// Mutably borrows the variable behind the proxy, unless it's currently borrowed
+    pub fn try_borrow_mut<'a>(&'a self) -> Option<BorrowRefMut<'a, S>> {
+        if self.borrows.get() == 0 {
+            self.borrows.set(WRITING);
+            Some(BorrowRefMut { cell: self })
+        } else {
+            None
+        }
+    }
+
+    /// Returns a mutable reference to the variable behind the proxy
+    ///
+    /// No borrow tracking is needed as the mutable borrow guarantees exclusive access.
+    pub fn get_mut(&mut self) -> &mut S::Type {
+        &mut self.singleton
+    }
+
+    /// Releases the proxy
+    pub fn into_inner(self) -> S {
+        self.singleton
+    }
+}
+
+impl<S> fmt::Debug for BorrowSingleton<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let borrows = self.borrows.get();
+        f.debug_struct("BorrowSingleton")
+            .field("borrows", &if borrows == WRITING { 0 } else { borrows })
+            .field("mutably_borrowed", &(borrows == WRITING))
+            .finish()
+    }
+}
+
+/// An immutable borrow of the variable behind a `BorrowSingleton`
+pub struct BorrowRef<'a, S>
+where
+    S: 'a,
+{
+    cell: &'a BorrowSingleton<S>,
+}
+
+impl<'a, S> Deref for BorrowRef<'a, S>
+where
+    S: SingletonMut,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*S::get() }
+    }
+}
+
+impl<'a, S> Drop for BorrowRef<'a, S> {
+    fn drop(&mut self) {
+        self.cell.borrows.set(self.cell.borrows.get() - 1);
+    }
+}
+
+/// A mutable borrow of the variable behind a `BorrowSingleton`
+pub struct BorrowRefMut<'a, S>
+where
+    S: 'a,
+{
+    cell: &'a BorrowSingleton<S>,
+}
+
+impl<'a, S> Deref for BorrowRefMut<'a, S>
+where
+    S: SingletonMut,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*S::get() }
+    }
+}
+
+impl<'a, S> DerefMut for BorrowRefMut<'a, S>
+where
+    S: SingletonMut,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut S::Type {
+        // NOTE the guard is the only way to reach the variable while it's mutably borrowed
+        unsafe { &mut *S::get() }
+    }
+}
+
+impl<'a, S> Drop for BorrowRefMut<'a, S> {
+    fn drop(&mut self) {
+        self.cell.borrows.set(0);
+    }
+}
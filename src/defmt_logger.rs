@@ -0,0 +1,194 @@
+//! A `defmt` global logger backed by a byte buffer singleton
+//!
+//! [`Logger`](struct.Logger.html) encodes defmt log frames into a ring buffer over the `[u8; N]`
+//! variable behind a singleton; the application drains the buffer with `read`, e.g. into a UART
+//! or a USB endpoint. The [`global_logger!`](../macro.global_logger.html) macro registers a
+//! `Logger` as the `#[defmt::global_logger]`.
+
+use core::cell::UnsafeCell;
+use core::marker::PhantomData;
+
+use critical_section::{self, RestoreState};
+use defmt::Encoder;
+
+use {export, SingletonMut};
+
+/// A `defmt` logger that writes into the `[u8; N]` variable behind the singleton `S`
+///
+/// Frames are discarded until `init` hands the proxy over to the logger. Bytes that don't fit in
+/// the buffer are discarded too; the encoding lets the host resynchronize at the next frame.
+pub struct Logger<S> {
+    state: UnsafeCell<State>,
+    _singleton: PhantomData<S>,
+}
+
+struct State {
+    /// `init` has been called
+    ready: bool,
+    /// A frame is being written
+    acquired: bool,
+    /// The state of the critical section held while a frame is being written
+    restore: RestoreState,
+    encoder: Encoder,
+    ring: Ring,
+}
+
+struct Ring {
+    /// The offset of the oldest unread byte
+    start: usize,
+    /// The number of unread bytes
+    len: usize,
+    /// The number of bytes discarded because the buffer was full
+    dropped: usize,
+}
+
+impl<S> Logger<S> {
+    /// Creates a logger that has no buffer yet
+    pub const fn new() -> Self {
+        Logger {
+            state: UnsafeCell::new(State {
+                ready: false,
+                acquired: false,
+                restore: RestoreState::invalid(),
+                encoder: Encoder::new(),
+                ring: Ring {
+                    start: 0,
+                    len: 0,
+                    dropped: 0,
+                },
+            }),
+            _singleton: PhantomData,
+        }
+    }
+
+    /// Runs `f` on the state of the logger inside a critical section
+    fn lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut State) -> R,
+    {
+        critical_section::with(|_| f(unsafe { &mut *self.state.get() }))
+    }
+}
+
+impl<S, const N: usize> Logger<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    /// Hands the proxy over to the logger; frames are written into the buffer from now on
+    pub fn init(&self, singleton: S) {
+        export::adopt(singleton);
+
+        self.lock(|state| state.ready = true)
+    }
+
+    /// Moves encoded log data out of the buffer and into `buf`; returns the number of bytes moved
+    pub fn read(&self, buf: &mut [u8]) -> usize {
+        self.lock(|state| {
+            let ring = &mut state.ring;
+            let n = buf.len().min(ring.len);
+
+            for byte in &mut buf[..n] {
+                *byte = unsafe { (*S::get())[ring.start] };
+                ring.start = (ring.start + 1) % N;
+            }
+            ring.len -= n;
+
+            n
+        })
+    }
+
+    /// Returns the number of bytes that haven't been read yet
+    pub fn len(&self) -> usize {
+        self.lock(|state| state.ring.len)
+    }
+
+    /// Returns `true` if all the log data has been read
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of bytes that have been discarded because the buffer was full
+    pub fn dropped(&self) -> usize {
+        self.lock(|state| state.ring.dropped)
+    }
+
+    /// `defmt::Logger::acquire`
+    #[doc(hidden)]
+    pub fn acquire(&self) {
+        // NOTE the critical section is held until `release`
+        let restore = unsafe { critical_section::acquire() };
+        let state = unsafe { &mut *self.state.get() };
+
+        if state.acquired {
+            panic!("defmt logger taken reentrantly");
+        }
+
+        state.acquired = true;
+        state.restore = restore;
+
+        let ready = state.ready;
+        let ring = &mut state.ring;
+        state
+            .encoder
+            .start_frame(|bytes| Self::push(ready, ring, bytes));
+    }
+
+    /// `defmt::Logger::flush`
+    ///
+    /// NOTE the application drains the buffer; waiting for it here would never end as the
+    /// logger holds a critical section
+    #[doc(hidden)]
+    pub unsafe fn flush(&self) {}
+
+    /// `defmt::Logger::release`
+    #[doc(hidden)]
+    pub unsafe fn release(&self) {
+        let state = &mut *self.state.get();
+
+        let ready = state.ready;
+        let ring = &mut state.ring;
+        state
+            .encoder
+            .end_frame(|bytes| Self::push(ready, ring, bytes));
+
+        state.acquired = false;
+        critical_section::release(state.restore);
+    }
+
+    /// `defmt::Logger::write`
+    #[doc(hidden)]
+    pub unsafe fn write(&self, bytes: &[u8]) {
+        let state = &mut *self.state.get();
+
+        let ready = state.ready;
+        let ring = &mut state.ring;
+        state
+            .encoder
+            .write(bytes, |bytes| Self::push(ready, ring, bytes));
+    }
+
+    /// Appends encoded `bytes` to the buffer
+    fn push(ready: bool, ring: &mut Ring, bytes: &[u8]) {
+        if !ready {
+            return;
+        }
+
+        for byte in bytes {
+            if ring.len == N {
+                ring.dropped += 1;
+            } else {
+                unsafe { (*S::get())[(ring.start + ring.len) % N] = *byte }
+                ring.len += 1;
+            }
+        }
+    }
+}
+
+impl<S> Default for Logger<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// NOTE the state is only accessed inside critical sections
+unsafe impl<S> Sync for Logger<S> {}
@@ -1,8 +1,270 @@
+use core::cell::UnsafeCell;
+
+use atomic::AtomicU8;
+
 pub use core::{
-    marker::PhantomData,
-    ops::{Deref, DerefMut},
+    borrow::{Borrow, BorrowMut},
+    cmp,
+    convert::{AsMut, AsRef, From},
+    fmt, hash,
+    marker::{PhantomData, PhantomPinned},
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut, Index, IndexMut},
+    pin::Pin,
+    ptr, slice,
+    sync::atomic::{self, Ordering},
 };
 
+#[cfg(feature = "track-owner")]
+pub use core::panic::Location;
+#[cfg(feature = "track-owner")]
+pub use owner::Owner;
+pub use refcount::RefCount;
 pub use stable_deref_trait::StableDeref;
 
+#[cfg(feature = "defmt")]
+pub use defmt::{Format, Formatter};
+#[cfg(feature = "take")]
+pub use flag::Flag;
+#[cfg(feature = "log")]
+pub use log::{set_logger, Log, SetLoggerError};
+#[cfg(feature = "rtic")]
+pub use rtic_core::Mutex;
+#[cfg(feature = "serde")]
+pub use serde::{Serialize, Serializer};
+#[cfg(feature = "ufmt")]
+pub use ufmt::{uDebug, uDisplay, uWrite, Formatter as UFormatter};
+#[cfg(feature = "zeroize")]
+pub use zeroize::{Zeroize, ZeroizeOnDrop};
+
 pub type NotSendOrSync = PhantomData<*const ()>;
+
+pub type NotSendOrSyncOrUnpin = PhantomData<(*const (), PhantomPinned)>;
+
+pub type NotSendOrSyncIn<S> = PhantomData<(*const (), S)>;
+
+/// Storage of an immutable singleton
+///
+/// Lets a plain `static` hold a value that's not `Sync`; the value is only reachable through the
+/// proxy, which is only `Sync` if the value is
+#[repr(transparent)]
+pub struct Static<T>(pub T);
+
+unsafe impl<T> Sync for Static<T> {}
+
+/// Storage of a mutable singleton
+///
+/// The value is only reachable through the raw pointer returned by `get` so references to it are
+/// only ever created by the proxy
+#[repr(transparent)]
+pub struct Cell<T>(UnsafeCell<T>);
+
+impl<T> Cell<T> {
+    #[inline]
+    pub const fn new(value: T) -> Self {
+        Cell(UnsafeCell::new(value))
+    }
+
+    #[inline]
+    pub const fn get(&self) -> *mut T {
+        self.0.get()
+    }
+}
+
+unsafe impl<T> Sync for Cell<T> {}
+
+/// Proxies of arrays whose elements can be taken one by one
+///
+/// # Safety
+///
+/// `flags` must return one flag per element, and the proxy must hold all of them
+#[cfg(feature = "take")]
+pub unsafe trait Elements: ::SingletonMut {
+    /// Whether dropping an element makes it available to `take_nth` again
+    const RELEASE: bool;
+
+    /// Returns the flags of the elements
+    fn flags() -> &'static [Flag];
+}
+
+/// Acquires all the `flags`; if that fails releases the ones it acquired and returns `false`
+#[cfg(feature = "take")]
+pub fn acquire_all(flags: &[Flag]) -> bool {
+    for (i, flag) in flags.iter().enumerate() {
+        if !flag.acquire() {
+            release_all(&flags[..i]);
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Releases all the `flags`
+#[cfg(feature = "take")]
+pub fn release_all(flags: &[Flag]) {
+    for flag in flags {
+        flag.release();
+    }
+}
+
+/// Takes over the `singleton` proxy on behalf of a type that manages its variable (e.g. `Pool`)
+///
+/// Dereferencing the proxy makes sure the variable is initialized (e.g. `lazy` singletons). The
+/// proxy is then forgotten as its destructor, if any, may make the singleton available again.
+#[doc(hidden)]
+#[inline]
+pub fn adopt<S>(singleton: S)
+where
+    S: ::SingletonMut,
+{
+    let _ = &*singleton;
+    mem::forget(singleton);
+}
+
+/// The variable of an `init` singleton has not been initialized
+const EMPTY: u8 = 0;
+/// The variable of an `init` singleton is being initialized
+const BUSY: u8 = 1;
+/// The variable of an `init` singleton has been initialized
+const READY: u8 = 2;
+
+/// Tracks the initialization of the variable of an `init` singleton
+pub struct InitState(AtomicU8);
+
+impl InitState {
+    pub const fn new() -> Self {
+        InitState(AtomicU8::new(EMPTY))
+    }
+
+    /// Moves the state from `EMPTY` to `BUSY`; returns `false` if that's not possible
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    pub fn begin(&self) -> bool {
+        self.0
+            .compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Moves the state from `EMPTY` to `BUSY`; returns `false` if that's not possible
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    pub fn begin(&self) -> bool {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            if self.0.load(Ordering::Relaxed) == EMPTY {
+                self.0.store(BUSY, Ordering::Relaxed);
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    #[inline]
+    pub fn finish(&self) {
+        self.0.store(READY, Ordering::Release)
+    }
+
+    #[inline]
+    pub fn is_ready(&self) -> bool {
+        self.0.load(Ordering::Acquire) == READY
+    }
+
+    #[inline]
+    pub fn reset(&self) {
+        self.0.store(EMPTY, Ordering::Relaxed)
+    }
+}
+
+impl Default for InitState {
+    fn default() -> Self {
+        InitState::new()
+    }
+}
+
+/// The magic number, and checksum, that mark a `noinit` variable as valid
+///
+/// Like the variable, the header is not initialized at startup so it keeps its contents across
+/// resets
+pub struct Header(UnsafeCell<MaybeUninit<[u32; 2]>>);
+
+impl Header {
+    #[inline]
+    pub const fn uninit() -> Self {
+        Header(UnsafeCell::new(MaybeUninit::uninit()))
+    }
+
+    /// Returns `true` if the header matches the value behind `ptr`
+    ///
+    /// NOTE after a power-on reset the header holds whatever was in memory; the volatile reads
+    /// keep the compiler from assuming anything about those contents
+    #[inline]
+    pub unsafe fn is_valid<T>(&self, ptr: *const T, crc: bool) -> bool {
+        let header = self.0.get() as *const u32;
+
+        ptr::read_volatile(header) == magic::<T>()
+            && (!crc || ptr::read_volatile(header.add(1)) == crc32(ptr))
+    }
+
+    /// Marks the value behind `ptr` as valid
+    #[inline]
+    pub unsafe fn seal<T>(&self, ptr: *const T, crc: bool) {
+        let header = self.0.get() as *mut u32;
+
+        if crc {
+            ptr::write_volatile(header.add(1), crc32(ptr));
+        }
+        ptr::write_volatile(header, magic::<T>());
+    }
+
+    /// Marks the variable as not holding a valid value
+    #[inline]
+    pub fn invalidate(&self) {
+        unsafe { ptr::write_volatile(self.0.get() as *mut u32, !magic::<()>()) }
+    }
+}
+
+unsafe impl Sync for Header {}
+
+/// The magic number of a valid `noinit` variable of type `T`
+///
+/// NOTE the size is mixed in so that a firmware update that changes the layout of the variable
+/// is likely to invalidate it
+fn magic<T>() -> u32 {
+    0x5afe_b007 ^ mem::size_of::<T>() as u32
+}
+
+/// The CRC-32 (IEEE 802.3) of the bytes of the value behind `ptr`
+unsafe fn crc32<T>(ptr: *const T) -> u32 {
+    let bytes = ptr as *const u8;
+
+    let mut crc = !0u32;
+    for i in 0..mem::size_of::<T>() {
+        crc ^= u32::from(ptr::read_volatile(bytes.add(i)));
+        for _ in 0..8 {
+            crc = if crc & 1 == 0 {
+                crc >> 1
+            } else {
+                (crc >> 1) ^ 0xedb8_8320
+            };
+        }
+    }
+    !crc
+}
+
+/// Overwrites the variable behind `ptr` with zeros in a way the compiler can't optimize away
+#[inline]
+pub unsafe fn wipe<T>(ptr: *mut T) {
+    let bytes = ptr as *mut u8;
+    for i in 0..mem::size_of::<T>() {
+        ptr::write_volatile(bytes.add(i), 0);
+    }
+    atomic::compiler_fence(Ordering::SeqCst);
+}
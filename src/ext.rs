@@ -0,0 +1,167 @@
+//! Extension trait
+
+use core::{mem, ops::DerefMut, ptr::NonNull};
+
+#[cfg(feature = "bytemuck")]
+use bytemuck::{Pod, PodCastError};
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Deserializer};
+
+use {Singleton, SingletonMut};
+
+/// Helper methods available on all singletons
+pub trait SingletonExt: Singleton {
+    /// Calls `f` with a shared reference to the variable behind this proxy
+    #[inline]
+    fn with<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&Self::Target) -> R,
+    {
+        f(&**self)
+    }
+
+    /// Calls `f` with a mutable reference to the variable behind this proxy
+    #[inline]
+    fn with_mut<R, F>(&mut self, f: F) -> R
+    where
+        Self: DerefMut,
+        F: FnOnce(&mut Self::Target) -> R,
+    {
+        f(&mut **self)
+    }
+
+    /// Returns a pointer to the variable behind this proxy
+    #[inline]
+    fn as_ptr(&self) -> *mut Self::Type {
+        Self::get()
+    }
+
+    /// Returns a non-null pointer to the variable behind this proxy
+    #[inline]
+    fn as_non_null(&self) -> NonNull<Self::Type> {
+        Self::get_nonnull()
+    }
+
+    /// Consumes this proxy and returns a `&'static mut` reference to a part of the variable
+    /// behind it
+    #[inline]
+    fn map<U, F>(self, f: F) -> &'static mut U
+    where
+        Self: Sized + SingletonMut,
+        Self::Type: 'static,
+        F: FnOnce(&'static mut Self::Type) -> &'static mut U,
+    {
+        f(self.unwrap())
+    }
+
+    /// Replaces the value of the variable behind this proxy, returning the old value
+    #[inline]
+    fn replace(&mut self, value: Self::Target) -> Self::Target
+    where
+        Self: DerefMut,
+        Self::Target: Sized,
+    {
+        mem::replace(&mut **self, value)
+    }
+
+    /// Swaps the value of the variable behind this proxy with `other`
+    #[inline]
+    fn swap(&mut self, other: &mut Self::Target)
+    where
+        Self: DerefMut,
+        Self::Target: Sized,
+    {
+        mem::swap(&mut **self, other)
+    }
+
+    /// Moves the value out of the variable behind this proxy, leaving `Default::default()` in its
+    /// place
+    #[inline]
+    fn take_value(&mut self) -> Self::Target
+    where
+        Self: DerefMut,
+        Self::Target: Default + Sized,
+    {
+        mem::take(&mut **self)
+    }
+
+    /// Consumes this proxy and moves the value out of the variable behind it, leaving
+    /// `Default::default()` in its place
+    ///
+    /// Use `replace` to leave a different value behind. Unlike `unwrap`, the proxy is dropped as
+    /// usual.
+    #[inline]
+    fn into_inner(mut self) -> Self::Target
+    where
+        Self: Sized + DerefMut,
+        Self::Target: Default + Sized,
+    {
+        self.take_value()
+    }
+
+    /// Returns the bytes of the variable behind this proxy
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    fn as_bytes(&self) -> &[u8]
+    where
+        Self::Target: Pod,
+    {
+        bytemuck::bytes_of(&**self)
+    }
+
+    /// Returns the bytes of the variable behind this proxy, for writing
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    fn as_bytes_mut(&mut self) -> &mut [u8]
+    where
+        Self: DerefMut,
+        Self::Target: Pod,
+    {
+        bytemuck::bytes_of_mut(&mut **self)
+    }
+
+    /// Reinterprets the variable behind this proxy as a slice of `U`
+    ///
+    /// Fails if the variable is not suitably aligned for `U` or its size is not a multiple of the
+    /// size of `U`
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    fn cast_slice<U>(&self) -> Result<&[U], PodCastError>
+    where
+        Self::Target: Pod,
+        U: Pod,
+    {
+        bytemuck::try_cast_slice(self.as_bytes())
+    }
+
+    /// Reinterprets the variable behind this proxy as a mutable slice of `U`
+    ///
+    /// Fails under the same conditions as `cast_slice`
+    #[cfg(feature = "bytemuck")]
+    #[inline]
+    fn cast_slice_mut<U>(&mut self) -> Result<&mut [U], PodCastError>
+    where
+        Self: DerefMut,
+        Self::Target: Pod,
+        U: Pod,
+    {
+        bytemuck::try_cast_slice_mut(self.as_bytes_mut())
+    }
+
+    /// Overwrites the variable behind this proxy with a value read from `deserializer`
+    ///
+    /// On error the variable is left untouched
+    #[cfg(feature = "serde")]
+    #[inline]
+    fn deserialize_into<'de, D>(&mut self, deserializer: D) -> Result<(), D::Error>
+    where
+        Self: DerefMut,
+        Self::Target: Deserialize<'de> + Sized,
+        D: Deserializer<'de>,
+    {
+        **self = Deserialize::deserialize(deserializer)?;
+        Ok(())
+    }
+}
+
+impl<S> SingletonExt for S where S: Singleton {}
@@ -0,0 +1,132 @@
+//! Runtime flag used by the checked (`take`) constructors
+
+use atomic::checked::AtomicBool;
+use atomic::Ordering;
+
+/// Tracks whether a singleton has been taken
+pub struct Flag {
+    taken: AtomicBool,
+}
+
+impl Flag {
+    /// Creates a new flag in the "not taken" state
+    #[cfg(not(all(loom, test)))]
+    pub const fn new() -> Self {
+        Flag {
+            taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Creates a new flag in the "not taken" state
+    // NOTE `loom`'s atomics can't be created in const context
+    #[cfg(all(loom, test))]
+    pub fn new() -> Self {
+        Flag {
+            taken: AtomicBool::new(false),
+        }
+    }
+
+    /// Marks the flag as taken
+    ///
+    /// Returns `false` if the flag was already taken
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    pub fn acquire(&self) -> bool {
+        !self.taken.swap(true, Ordering::Acquire)
+    }
+
+    /// Marks the flag as taken
+    ///
+    /// Returns `false` if the flag was already taken
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    pub fn acquire(&self) -> bool {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            if self.taken.load(Ordering::Relaxed) {
+                false
+            } else {
+                self.taken.store(true, Ordering::Relaxed);
+                true
+            }
+        })
+    }
+
+    /// Marks the flag as not taken
+    #[inline]
+    pub fn release(&self) {
+        self.taken.store(false, Ordering::Release)
+    }
+}
+
+impl Default for Flag {
+    fn default() -> Self {
+        Flag::new()
+    }
+}
+
+// Model checks of the take / release logic
+//
+// Run with `RUSTFLAGS="--cfg loom" cargo test --lib --features take --release`
+#[cfg(all(loom, test))]
+mod tests {
+    use loom::cell::UnsafeCell;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    use super::Flag;
+
+    /// Only one of two contexts that race to `take` a singleton gets it
+    #[test]
+    fn take_once() {
+        loom::model(|| {
+            let flag = Arc::new(Flag::new());
+
+            let other = {
+                let flag = flag.clone();
+                thread::spawn(move || flag.acquire())
+            };
+
+            let here = flag.acquire();
+            let there = other.join().unwrap();
+
+            assert!(here ^ there);
+        });
+    }
+
+    /// Writes made before `release` are visible to the context that `take`s the singleton next
+    #[test]
+    fn release_then_take() {
+        loom::model(|| {
+            let flag = Arc::new(Flag::new());
+            let variable = Arc::new(UnsafeCell::new(0));
+
+            assert!(flag.acquire());
+
+            let other = {
+                let flag = flag.clone();
+                let variable = variable.clone();
+                thread::spawn(move || {
+                    if flag.acquire() {
+                        // NOTE loom reports a data race if this access is not ordered after the
+                        // write
+                        let value = variable.with(|ptr| unsafe { *ptr });
+                        assert_eq!(value, 1);
+                        flag.release();
+                    }
+                })
+            };
+
+            variable.with_mut(|ptr| unsafe { *ptr = 1 });
+            flag.release();
+
+            other.join().unwrap();
+        });
+    }
+}
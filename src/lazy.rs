@@ -0,0 +1,112 @@
+//! Lazily initialized singletons
+//!
+//! [`Lazy`](struct.Lazy.html) pairs the proxy of a `MaybeUninit<T>` variable with a function that
+//! computes its value the first time it's dereferenced. Unlike the `lazy` argument of the
+//! `Singleton` attribute the function can be a closure that captures its environment.
+
+use core::{
+    cell::Cell,
+    fmt,
+    mem::MaybeUninit,
+    ops::{Deref, DerefMut},
+};
+
+use SingletonMut;
+
+/// A value, stored in the `MaybeUninit<T>` variable behind the singleton `S`, that's computed by
+/// `F` on first access
+pub struct Lazy<S, F> {
+    singleton: S,
+    init: Cell<Option<F>>,
+    ready: Cell<bool>,
+}
+
+impl<S, T, F> Lazy<S, F>
+where
+    S: SingletonMut<Type = MaybeUninit<T>>,
+    F: FnOnce() -> T,
+{
+    /// Wraps the proxy; `init` computes the value on first access
+    pub fn new(singleton: S, init: F) -> Self {
+        Lazy {
+            singleton,
+            init: Cell::new(Some(init)),
+            ready: Cell::new(false),
+        }
+    }
+
+    /// Computes the value, if that hasn't been done yet, and returns a reference to it
+    ///
+    /// # Panics
+    ///
+    /// This function panics if it's called from the initialization function or if a previous call
+    /// to the initialization function panicked
+    pub fn force(this: &Self) -> &T {
+        if !this.ready.get() {
+            let init = this
+                .init
+                .take()
+                .expect("`Lazy` was accessed during, or after a failed, initialization");
+
+            unsafe { (*S::get()).as_mut_ptr().write(init()) }
+            this.ready.set(true);
+        }
+
+        unsafe { &*(*S::get()).as_ptr() }
+    }
+
+    /// Returns the value, if it has been computed
+    pub fn get(this: &Self) -> Option<&T> {
+        if this.ready.get() {
+            Some(unsafe { &*(*S::get()).as_ptr() })
+        } else {
+            None
+        }
+    }
+
+    /// Releases the proxy
+    ///
+    /// The value, if any, stays in the variable; it's not dropped.
+    pub fn into_inner(this: Self) -> S {
+        this.singleton
+    }
+}
+
+impl<S, T, F> Deref for Lazy<S, F>
+where
+    S: SingletonMut<Type = MaybeUninit<T>>,
+    F: FnOnce() -> T,
+{
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        Lazy::force(self)
+    }
+}
+
+impl<S, T, F> DerefMut for Lazy<S, F>
+where
+    S: SingletonMut<Type = MaybeUninit<T>>,
+    F: FnOnce() -> T,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        Lazy::force(self);
+        unsafe { &mut *(*self.singleton).as_mut_ptr() }
+    }
+}
+
+impl<S, T, F> fmt::Debug for Lazy<S, F>
+where
+    S: SingletonMut<Type = MaybeUninit<T>>,
+    T: fmt::Debug,
+    F: FnOnce() -> T,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match Lazy::get(self) {
+            Some(value) => f.debug_tuple("Lazy").field(value).finish(),
+            None => f.write_str("Lazy(<uninit>)"),
+        }
+    }
+}
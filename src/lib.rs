@@ -15,7 +15,8 @@
 //! # Examples
 //!
 //! The `Singleton` attribute creates a proxy (`struct`) for the given `static mut` variable and
-//! implements the `Singleton`, `Deref`, `DerefMut` and `StableDeref`s traits for it.
+//! implements the `Singleton`, `SingletonMut`, `Deref`, `DerefMut` and `StableDeref`s traits for
+//! it. Generic code can use the `SingletonMut` bound to ask for a singleton it can mutate.
 //!
 //! ```
 //! use owned_singleton::Singleton;
@@ -32,6 +33,1436 @@
 //! assert_eq!(*bar, 1);
 //! ```
 //!
+//! `leak` does the same as `unwrap`, and the proxy can also be converted with `From` / `Into`,
+//! which reads better when handing the variable to an API that wants a `&'static mut` reference.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut RX_BUF: [u8; 64] = [0; 64];
+//!
+//! // e.g. a HAL constructor
+//! fn start_transfer(buf: &'static mut [u8; 64]) {
+//!     buf[0] = 1;
+//! }
+//!
+//! start_transfer(unsafe { RX_BUF::new() }.into());
+//!
+//! let buf = unsafe { RX_BUF::new() }.leak();
+//! assert_eq!(buf[0], 1);
+//! ```
+//!
+//! Doc comments on the `static mut` variable end up on the proxy struct so the proxy can be
+//! documented like any other type.
+//!
+//! ```
+//! #![deny(missing_docs)]
+//! //! Crate docs
+//!
+//! use owned_singleton::Singleton;
+//!
+//! /// Receive buffer
+//! #[Singleton]
+//! pub static mut RX_BUF: [u8; 64] = [0; 64];
+//! # fn main() {}
+//! ```
+//!
+//! The proxy struct is opaque. Use the `derive` argument to apply derive macros to it.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(derive(Debug))]
+//! static mut FOO: u32 = 0;
+//!
+//! let foo = unsafe { FOO::new() };
+//! println!("{:?}", foo);
+//! ```
+//!
+//! By default the proxy struct has the same name as the `static mut` variable. Use the `name`
+//! argument to give the proxy a different name.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(name = "UartBuf")]
+//! static mut UART_BUF: [u8; 128] = [0; 128];
+//!
+//! let buf: UartBuf = unsafe { UartBuf::new() };
+//! assert_eq!(buf.len(), 128);
+//! ```
+//!
+//! The `static mut` variable behind the proxy is given a hidden name, derived from the crate, the
+//! tokens of the item and their location in the source code, to prevent direct access to it. This
+//! makes the variable hard to find in map files, `nm` output or a debugger. The
+//! `readable` argument renames the variable to `__NAME` and exports it under the
+//! `path::to::module::NAME` symbol instead.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! // exported as `$crate_name::app::COUNTER`
+//! mod app {
+//!     use owned_singleton::Singleton;
+//!
+//!     #[Singleton(readable)]
+//!     pub static mut COUNTER: u32 = 0;
+//! }
+//!
+//! let counter = unsafe { app::COUNTER::new() };
+//! assert_eq!(*counter, 0);
+//! ```
+//!
+//! Identical singletons declared in different places, or in different versions of a crate, get
+//! different hidden names, so several copies of the same singleton can be linked into a program,
+//! e.g. when two versions of a crate end up in the dependency graph.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! fn rx() {
+//!     #[Singleton]
+//!     static mut BUFFER: [u8; 64] = [0; 64];
+//! }
+//!
+//! fn tx() {
+//!     #[Singleton]
+//!     static mut BUFFER: [u8; 32] = [0; 32];
+//! }
+//! ```
+//!
+//! Use the `deny_duplicates` argument to turn that into an error: the variable is exported under
+//! the `path::to::module::NAME` symbol and kept even if unused, so a second declaration of a
+//! singleton with the same name in the same module results in a duplicate symbol error.
+//!
+//! ```compile_fail
+//! use owned_singleton::Singleton;
+//!
+//! fn rx() {
+//!     #[Singleton(deny_duplicates)]
+//!     static mut BUFFER: [u8; 64] = [0; 64];
+//! }
+//!
+//! fn tx() {
+//!     // error: symbol `rust_out::BUFFER` is already defined
+//!     #[Singleton(deny_duplicates)]
+//!     static mut BUFFER: [u8; 32] = [0; 32];
+//! }
+//! ```
+//!
+//! To reference the variable from C code, assembly or a debugger script pick its symbol with the
+//! `export_name` argument, or use the `no_mangle` argument to export it under its own name.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! // C: `extern uint8_t g_uart_state[16];`
+//! #[Singleton(export_name = "g_uart_state")]
+//! static mut UART_STATE: [u8; 16] = [0; 16];
+//!
+//! let state = unsafe { UART_STATE::new() };
+//! assert_eq!(state.len(), 16);
+//! ```
+//!
+//! Alternatively, the `ffi` argument generates `extern "C"` accessor functions for C code: a
+//! `NAME_get` function that returns a pointer to the variable plus a getter and a setter for each
+//! listed field. C code must not use them while the proxy is in use.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[repr(C)]
+//! pub struct UartState {
+//!     baud_rate: u32,
+//!     errors: u32,
+//! }
+//!
+//! // C: `UartState *UART_get(void);`, `uint32_t UART_get_errors(void);`, etc.
+//! #[Singleton(ffi(errors: u32))]
+//! static mut UART: UartState = UartState { baud_rate: 9_600, errors: 0 };
+//!
+//! unsafe {
+//!     UART_set_errors(1);
+//!     assert_eq!(UART_get_errors(), 1);
+//!     assert_eq!((*UART_get()).baud_rate, 9_600);
+//! }
+//! ```
+//!
+//! Use the `section` argument to place the variable in a specific memory region, e.g. tightly
+//! coupled memory. The section must be defined in your linker script.
+//!
+//! ```ignore
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(section = ".dtcm")]
+//! static mut SAMPLES: [i16; 1024] = [0; 1024];
+//! ```
+//!
+//! Large buffers that don't need an initial value can use the `uninit` argument. The variable
+//! must have type `MaybeUninit<T>`; it's placed in the `.uninit` linker section so it takes no
+//! space in Flash and it's not zeroed at startup. The proxy gets `init_zeroed` and `write`
+//! methods to initialize the buffer.
+//!
+//! ```
+//! use std::mem::MaybeUninit;
+//!
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(uninit)]
+//! static mut BUF: MaybeUninit<[u8; 64 * 1024]> = MaybeUninit::uninit();
+//!
+//! let mut buf = unsafe { BUF::new() };
+//! let bytes: &mut [u8; 64 * 1024] = unsafe { buf.init_zeroed() };
+//! assert!(bytes.iter().all(|byte| *byte == 0));
+//!
+//! buf.write([1; 64 * 1024]);
+//! ```
+//!
+//! State that must survive a warm reset can use the `noinit` argument. The variable is declared
+//! without an initializer and it's placed in the `.uninit` section together with a magic number
+//! that records whether it holds a valid value; `noinit(crc)` also checks a CRC-32 of the value.
+//!
+//! ```
+//! use std::mem::MaybeUninit;
+//!
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(noinit(crc))]
+//! static mut BOOTS: MaybeUninit<u32>;
+//!
+//! let mut boots = unsafe { BOOTS::new() };
+//!
+//! // first boot, or the contents were corrupted
+//! boots.invalidate();
+//! if !boots.modify(|count| *count += 1) {
+//!     boots.write(1);
+//! }
+//! assert_eq!(boots.valid(), Some(&1));
+//!
+//! // after a reset
+//! assert!(boots.modify(|count| *count += 1));
+//! assert_eq!(boots.valid(), Some(&2));
+//!
+//! boots.invalidate();
+//! assert!(!boots.is_valid());
+//! ```
+//!
+//! The `panic_persist` module uses the same mechanism to keep the panic message around: call
+//! `panic_persist::report` from the `#[panic_handler]` and `panic_persist::get_panic_message`
+//! after the reset. `report_fmt` does the same from a `std` panic hook.
+//!
+//! ```
+//! use std::panic;
+//!
+//! use owned_singleton::panic_persist;
+//!
+//! panic::set_hook(Box::new(|info| panic_persist::report_fmt(format_args!("{}", info))));
+//! let _ = panic::catch_unwind(|| panic!("sensor timed out"));
+//! let _ = panic::take_hook();
+//!
+//! // after the reset
+//! let message = panic_persist::get_panic_message().unwrap();
+//! assert!(message.contains("sensor timed out"));
+//!
+//! // handed out only once
+//! assert!(panic_persist::get_panic_message().is_none());
+//!
+//! message.clear();
+//! ```
+//!
+//! Values that can't be computed at compile time can be initialized on first use. Declare the
+//! `static mut` variable without an initializer and pass an initialization function to the `lazy`
+//! argument; the function runs the first time the proxy is dereferenced.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! fn squares() -> [u32; 16] {
+//!     let mut table = [0; 16];
+//!     for (i, square) in table.iter_mut().enumerate() {
+//!         *square = (i * i) as u32;
+//!     }
+//!     table
+//! }
+//!
+//! #[Singleton(lazy = squares)]
+//! static mut SQUARES: [u32; 16];
+//!
+//! let squares = unsafe { SQUARES::new() };
+//! assert_eq!(squares[3], 9);
+//! ```
+//!
+//! Values that are computed at runtime, but by the application, can use the `typestate`
+//! argument instead. The proxy of the `MaybeUninit<T>` variable starts out as `FOO<Uninit>`,
+//! which has no access to the variable, and turns into `FOO<Init>` (or simply `FOO`), which
+//! dereferences to `T`, once it's initialized.
+//!
+//! ```
+//! use std::mem::MaybeUninit;
+//!
+//! use owned_singleton::typestate::Uninit;
+//! use owned_singleton::Singleton;
+//!
+//! pub struct Clocks {
+//!     sysclk: u32,
+//! }
+//!
+//! #[Singleton(typestate)]
+//! static mut CLOCKS: MaybeUninit<Clocks> = MaybeUninit::uninit();
+//!
+//! let clocks: CLOCKS<Uninit> = unsafe { CLOCKS::uninit() };
+//! // `clocks.sysclk` would be rejected at compile time
+//!
+//! let clocks: CLOCKS = clocks.init(Clocks { sysclk: 64_000_000 });
+//! assert_eq!(clocks.sysclk, 64_000_000);
+//! ```
+//!
+//! With the `init` argument the variable is declared without an initializer and the proxy is
+//! obtained by initializing it at runtime; `init` returns the value back if the variable has
+//! already been initialized.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! pub struct Uart {
+//!     divisor: u32,
+//! }
+//!
+//! #[Singleton(init)]
+//! static mut UART: Uart;
+//!
+//! fn sysclk() -> u32 {
+//!     16_000_000
+//! }
+//!
+//! assert!(!UART::is_initialized());
+//!
+//! let uart = UART::init(Uart { divisor: sysclk() / 115_200 }).ok().unwrap();
+//! assert_eq!(uart.divisor, 138);
+//!
+//! assert!(UART::is_initialized());
+//! assert!(UART::init(Uart { divisor: 1 }).is_err());
+//! ```
+//!
+//! `init_for_task` is the equivalent of `static_cell`'s `StaticCell::init`: it initializes the
+//! variable and returns a `&'static mut` reference to it, panicking if the variable has already
+//! been initialized. This is what Embassy task spawners and driver constructors expect.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! pub struct Channel {
+//!     pending: u32,
+//! }
+//!
+//! #[Singleton(init)]
+//! static mut CHANNEL: Channel;
+//!
+//! // e.g. `spawner.spawn(consumer(channel))`
+//! fn consumer(channel: &'static mut Channel) {
+//!     channel.pending = 0;
+//! }
+//!
+//! let channel: &'static mut Channel = CHANNEL::init_for_task(Channel { pending: 3 });
+//! consumer(channel);
+//!
+//! assert!(std::panic::catch_unwind(|| CHANNEL::init_for_task(Channel { pending: 0 })).is_err());
+//! ```
+//!
+//! The [`singleton!`](macro.singleton.html) macro declares an `init` variable in expression
+//! position, like `cortex_m::singleton!` does, for quick declarations inside init code.
+//!
+//! Projects that would rather not build a proc macro can use the
+//! [`declare_singleton!`](macro.declare_singleton.html) macro instead, which covers plain `static
+//! mut` variables, and disable the default `macros` feature.
+//!
+//! Proxies over storage that neither macro can declare, e.g. variables emitted by a code generator,
+//! can be written by hand; [`impl_singleton!`](macro.impl_singleton.html) implements the traits
+//! once the proxy and the address of its variable are given.
+//!
+//! Library crates that need singleton storage of a generic type can use
+//! [`tagged::SingletonCell`](tagged/struct.SingletonCell.html), whose uniqueness is tied to a tag
+//! type rather than to a `static` variable declared with the attribute.
+//!
+//! Memory at a fixed address, like a mailbox shared with another core or a buffer whose location
+//! is mandated by the hardware, can be owned too. Declare the `static mut` variable without an
+//! initializer and pass the address to the `address` argument; the proxy then points to that
+//! address and no storage is allocated for the variable. The address must be suitably aligned for
+//! the type of the variable.
+//!
+//! ```ignore
+//! use owned_singleton::Singleton;
+//!
+//! #[repr(C)]
+//! pub struct Mailbox {
+//!     command: u32,
+//!     argument: u32,
+//! }
+//!
+//! #[Singleton(address = 0x2000_c000)]
+//! static mut MAILBOX: Mailbox;
+//!
+//! let mut mailbox = unsafe { MAILBOX::new() };
+//! mailbox.argument = 42;
+//! mailbox.command = 1;
+//! ```
+//!
+//! [`mmio::Owned<T, ADDR>`](mmio/struct.Owned.html) is the same kind of proxy without a `static`
+//! declaration: the address is a const generic parameter of the type, which suits peripherals and
+//! other regions that drivers want to be generic over.
+//!
+//! The `align` argument over-aligns the variable. The proxy still dereferences to the original
+//! type so there's no need to write a `#[repr(align)]` wrapper by hand.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! // DMA descriptors must be 32-byte aligned
+//! #[Singleton(align = 32)]
+//! static mut DESCRIPTORS: [u32; 16] = [0; 16];
+//!
+//! let descriptors = unsafe { DESCRIPTORS::new() };
+//! assert_eq!(descriptors.as_ptr() as usize % 32, 0);
+//! ```
+//!
+//! The `stack` argument declares the stack of an RTOS task or executor: the array is 8-byte
+//! aligned and the proxy gets `bottom`, `top` and `size` methods. With `stack(paint = ..)` the
+//! proxy can also fill the stack with a byte pattern and later report how much of it has been
+//! used.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(stack(paint = 0xa5))]
+//! static mut TASK_STACK: [u32; 256] = [0; 256];
+//!
+//! let mut stack = unsafe { TASK_STACK::new() };
+//! assert_eq!(stack.top() as usize - stack.bottom() as usize, 1024);
+//! assert_eq!(stack.bottom() as usize % 8, 0);
+//!
+//! stack.paint();
+//! assert_eq!(stack.high_water_mark(), 0);
+//!
+//! // e.g. the task runs and uses 40 bytes of its stack
+//! stack[246..].iter_mut().for_each(|word| *word = 0);
+//! assert_eq!(stack.high_water_mark(), 40);
+//! ```
+//!
+//! The `max_size` argument puts a budget on the size of the variable: compilation fails if the
+//! variable grows past the given number of bytes.
+//!
+//! ```compile_fail
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(max_size = 4096)]
+//! static mut BUF: [u8; 8192] = [0; 8192];
+//! ```
+//!
+//! To put a budget on a whole memory region use the [`budget!`](macro.budget.html) macro, which
+//! checks the combined size of a list of singletons.
+//!
+//! The `Singleton` trait exposes the name of the proxy and the size and alignment of the variable
+//! as associated constants, which generic code can use for diagnostics.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(align = 32)]
+//! static mut RX_RING: [u32; 16] = [0; 16];
+//!
+//! fn report<S: Singleton>() -> (&'static str, usize, usize) {
+//!     (S::NAME, S::SIZE, S::ALIGN)
+//! }
+//!
+//! assert_eq!(report::<RX_RING>(), ("RX_RING", 64, 32));
+//! ```
+//!
+//! The variable is exported (`#[export_name]`) so that it's visible to the linker. If you don't
+//! need a linker-visible name use the `no_export` argument. In that case the variable is not
+//! exported; instead it's hidden inside an anonymous `const` item so it can't be named at all
+//! and the linker is free to internalize it.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(no_export)]
+//! static mut FOO: u32 = 0;
+//!
+//! let mut foo = unsafe { FOO::new() };
+//! *foo += 1;
+//! assert_eq!(*foo, 1);
+//! ```
+//!
+//! The `count` argument declares several independent singletons that share a declaration. The
+//! proxies are named after the `static mut` variable plus a numeric suffix.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! // per channel DMA buffers: `BUF0`, `BUF1`, `BUF2` and `BUF3`
+//! #[Singleton(count = 4)]
+//! static mut BUF: [u8; 512] = [0; 512];
+//!
+//! let mut buf0 = unsafe { BUF0::new() };
+//! let buf3 = unsafe { BUF3::new() };
+//! buf0[0] = 1;
+//! assert_eq!(buf3[0], 0);
+//! ```
+//!
+//! The `split` argument generates one proxy per field of a struct so that different tasks can own
+//! different parts of the same `static mut` variable. The fields and their types must be listed
+//! in the argument; the field proxies are named after the `static mut` variable and the field.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! pub struct State {
+//!     rx_count: u32,
+//!     tx_count: u32,
+//! }
+//!
+//! #[Singleton(split(rx_count: u32, tx_count: u32))]
+//! static mut STATE: State = State { rx_count: 0, tx_count: 0 };
+//!
+//! let state = unsafe { STATE::new() };
+//! let (mut rx, mut tx): (STATE_RX_COUNT, STATE_TX_COUNT) = state.split();
+//! *rx += 1;
+//! *tx += 2;
+//! assert_eq!((*rx, *tx), (1, 2));
+//! ```
+//!
+//! Variables that are also accessed by a DMA engine or a debugger shouldn't be cached by the
+//! compiler. The `volatile` argument replaces the `Deref` implementations with `read`, `write` and
+//! `modify` methods that use volatile operations. Note that such proxies don't implement the
+//! `Singleton` trait.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(volatile)]
+//! static mut STATUS: u32 = 0;
+//!
+//! let mut status = unsafe { STATUS::new() };
+//! status.write(1);
+//! status.modify(|status| *status |= 2);
+//! assert_eq!(status.read(), 3);
+//! ```
+//!
+//! On multicore systems the `per_cpu` argument creates one copy of the variable per core. The
+//! `core` argument names a function that returns the index of the core that's running the code;
+//! the proxy uses it to select the copy that belongs to the current core. Each core can create
+//! its own proxy, which can't be sent to other cores.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! fn current_core() -> usize {
+//!     // e.g. read the MPIDR register on ARMv7-A
+//!     0
+//! }
+//!
+//! #[Singleton(per_cpu = 2, core = current_core)]
+//! static mut IRQ_COUNT: u32 = 0;
+//!
+//! let mut count = unsafe { IRQ_COUNT::new() };
+//! *count += 1;
+//! assert_eq!(*count, 1);
+//! ```
+//!
+//! `freeze` and `token` are rejected at compile time: the `Shared` handles and `Token`s they
+//! return can be sent to other cores, where they would refer to the copy of that core.
+//!
+//! ```compile_fail
+//! use owned_singleton::Singleton;
+//!
+//! fn current_core() -> usize {
+//!     0
+//! }
+//!
+//! #[Singleton(per_cpu = 2, core = current_core)]
+//! static mut CONFIG: u32 = 0;
+//!
+//! // error: `per_cpu` singletons can't be frozen or turned into tokens
+//! let config = unsafe { CONFIG::new() }.freeze();
+//! ```
+//!
+//! Code that can't reach the proxy, like a panic handler, can still access the variable through
+//! the `unsafe` `steal` method.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut LOG: [u8; 32] = [0; 32];
+//!
+//! // e.g. in a panic handler; the owner of the proxy won't run again
+//! let log: &'static mut [u8; 32] = unsafe { LOG::steal() };
+//! log[0] = b'!';
+//! ```
+//!
+//! The [`SingletonExt`](trait.SingletonExt.html) trait, implemented for all singletons, provides
+//! closure based accessors and other helpers to generic code.
+//!
+//! ```
+//! use owned_singleton::{Singleton, SingletonExt};
+//!
+//! #[Singleton]
+//! static mut COUNTERS: [u32; 4] = [0; 4];
+//!
+//! fn bump<S>(counters: &mut S)
+//! where
+//!     S: SingletonExt<Target = [u32; 4]> + std::ops::DerefMut,
+//! {
+//!     counters.with_mut(|counters| counters[0] += 1);
+//! }
+//!
+//! let mut counters = unsafe { COUNTERS::new() };
+//! bump(&mut counters);
+//! assert_eq!(counters.with(|counters| counters[0]), 1);
+//!
+//! let first: &'static mut u32 = counters.map(|counters| &mut counters[0]);
+//! assert_eq!(*first, 1);
+//! ```
+//!
+//! It also wraps `core::mem::{replace, swap, take}` so values can be moved in and out of the
+//! variable.
+//!
+//! ```
+//! use owned_singleton::{Singleton, SingletonExt};
+//!
+//! #[Singleton]
+//! static mut PENDING: Option<u32> = None;
+//!
+//! let mut pending = unsafe { PENDING::new() };
+//! assert_eq!(pending.replace(Some(1)), None);
+//!
+//! let mut other = Some(2);
+//! pending.swap(&mut other);
+//! assert_eq!(other, Some(1));
+//!
+//! assert_eq!(pending.take_value(), Some(2));
+//! assert_eq!(*pending, None);
+//!
+//! pending.replace(Some(3));
+//! assert_eq!(pending.into_inner(), Some(3));
+//! ```
+//!
+//! With the `const-ptr` feature the proxies also implement
+//! [`ConstSingleton`](trait.ConstSingleton.html), whose `PTR` constant holds the address of the
+//! variable. Unlike `Singleton::get`, `PTR` can be used to initialize other `static` variables.
+//!
+//! ```
+//! # #[cfg(feature = "const-ptr")]
+//! # fn main() {
+//! use owned_singleton::{ConstSingleton, Singleton};
+//!
+//! #[Singleton]
+//! static mut RX_BUF: [u8; 128] = [0; 128];
+//!
+//! // a DMA descriptor that embeds the address of the buffer
+//! pub struct Descriptor {
+//!     buffer: *mut [u8; 128],
+//! }
+//!
+//! unsafe impl Sync for Descriptor {}
+//!
+//! static RX_DESCRIPTOR: Descriptor = Descriptor { buffer: RX_BUF::PTR };
+//!
+//! assert_eq!(RX_DESCRIPTOR.buffer, RX_BUF::get());
+//! # }
+//! # #[cfg(not(feature = "const-ptr"))]
+//! # fn main() {}
+//! ```
+//!
+//! Likewise, with the `defmt` feature proxies implement `defmt::Format` if the type of the variable
+//! does.
+//!
+//! ```
+//! # #[cfg(feature = "defmt")]
+//! extern crate defmt;
+//!
+//! # #[cfg(feature = "defmt")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut RSSI: i8 = -70;
+//!
+//! // e.g. `defmt::info!("{}", rssi)`
+//! fn log<T: defmt::Format>(_value: &T) {}
+//!
+//! let rssi = unsafe { RSSI::new() };
+//! log(&rssi);
+//! # }
+//! # #[cfg(not(feature = "defmt"))]
+//! # fn main() {}
+//! ```
+//!
+//! The `defmt-logger` feature adds [`defmt_logger::Logger`](defmt_logger/struct.Logger.html), a
+//! `defmt` global logger whose transport buffer is a byte buffer singleton, and the
+//! [`global_logger!`](macro.global_logger.html) macro that registers it.
+//!
+//! The `ufmt` feature does the same for `ufmt`'s `uDebug` and `uDisplay` traits.
+//!
+//! ```
+//! # #[cfg(feature = "ufmt")]
+//! extern crate ufmt;
+//!
+//! # #[cfg(feature = "ufmt")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut UPTIME: u32 = 42;
+//!
+//! let uptime = unsafe { UPTIME::new() };
+//!
+//! let mut s = String::new();
+//! ufmt::uwrite!(s, "{} {:?}", uptime, uptime).unwrap();
+//! assert_eq!(s, "42 42");
+//! # }
+//! # #[cfg(not(feature = "ufmt"))]
+//! # fn main() {}
+//! ```
+//!
+//! With the `rtic` feature the proxies of `static mut` variables implement `rtic_core::Mutex`, the
+//! trait behind RTIC's shared resources, so code written against that trait accepts both RTIC
+//! resources and singletons; locking a proxy simply hands out the variable as the proxy already
+//! owns it. In the other direction, proxies with the `Send` argument can be used as RTIC `local`
+//! resources directly, with no `&'static mut` shim.
+//!
+//! ```
+//! # #[cfg(feature = "rtic")]
+//! extern crate rtic_core;
+//!
+//! # #[cfg(feature = "rtic")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//! use rtic_core::Mutex;
+//!
+//! // also works with an RTIC shared resource, e.g. `cx.shared.ticks`
+//! fn tick(mut ticks: impl Mutex<T = u32>) {
+//!     ticks.lock(|ticks| *ticks += 1);
+//! }
+//!
+//! #[Singleton]
+//! static mut TICKS: u32 = 0;
+//!
+//! let mut ticks = unsafe { TICKS::new() };
+//! tick(&mut ticks);
+//! assert_eq!(*ticks, 1);
+//! # }
+//! # #[cfg(not(feature = "rtic"))]
+//! # fn main() {}
+//! ```
+//!
+//! With the `log` feature the proxy of a variable whose type implements `log::Log` gets a
+//! `set_as_logger` method that registers the variable as the global logger, so the logger state
+//! doesn't need a separate `static` of its own.
+//!
+//! ```
+//! # #[cfg(feature = "log")]
+//! #[macro_use]
+//! extern crate log;
+//!
+//! # #[cfg(feature = "log")]
+//! # fn main() {
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! use log::{LevelFilter, Log, Metadata, Record};
+//! use owned_singleton::Singleton;
+//!
+//! struct Counter {
+//!     records: AtomicUsize,
+//! }
+//!
+//! impl Log for Counter {
+//!     fn enabled(&self, _: &Metadata) -> bool {
+//!         true
+//!     }
+//!
+//!     fn log(&self, _: &Record) {
+//!         self.records.fetch_add(1, Ordering::Relaxed);
+//!     }
+//!
+//!     fn flush(&self) {}
+//! }
+//!
+//! #[Singleton]
+//! static LOGGER: Counter = Counter {
+//!     records: AtomicUsize::new(0),
+//! };
+//!
+//! unsafe { LOGGER::new() }.set_as_logger().unwrap();
+//! log::set_max_level(LevelFilter::Info);
+//!
+//! info!("booted");
+//! assert_eq!(unsafe { LOGGER::new() }.records.load(Ordering::Relaxed), 1);
+//! # }
+//! # #[cfg(not(feature = "log"))]
+//! # fn main() {}
+//! ```
+//!
+//! With the `trace` feature the proxies count the reads and writes made through them and call the
+//! hook set with [`trace::set_hook`](trace/fn.set_hook.html) on every access, which helps finding
+//! hot singletons or accesses from unexpected contexts.
+//!
+//! ```
+//! # #[cfg(feature = "trace")]
+//! # fn main() {
+//! use owned_singleton::trace::{self, Access};
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut RX_COUNT: u32 = 0;
+//!
+//! fn hook(name: &'static str, access: Access) {
+//!     // e.g. check the current interrupt priority, or emit an ITM packet
+//!     let _ = (name, access);
+//! }
+//!
+//! trace::set_hook(hook);
+//!
+//! let mut count = unsafe { RX_COUNT::new() };
+//! *count += 1;
+//! let _ = *count;
+//!
+//! assert_eq!(RX_COUNT::counters().writes(), 1);
+//! assert_eq!(RX_COUNT::counters().reads(), 1);
+//! # }
+//! # #[cfg(not(feature = "trace"))]
+//! # fn main() {}
+//! ```
+//!
+//! [`Writer`](struct.Writer.html) turns the proxy of a byte buffer into a `fmt::Write` sink, e.g.
+//! to format panic messages into statically allocated memory.
+//!
+//! ```
+//! use std::fmt::Write;
+//!
+//! use owned_singleton::{Singleton, Writer};
+//!
+//! #[Singleton]
+//! static mut PANIC_MSG: [u8; 16] = [0; 16];
+//!
+//! let mut w = Writer::new(unsafe { PANIC_MSG::new() });
+//! write!(w, "error {}", 42).unwrap();
+//! assert_eq!(w.as_str(), "error 42");
+//!
+//! // the message is truncated once the buffer is full
+//! assert!(write!(w, ": out of memory").is_err());
+//! assert_eq!(w.as_str(), "error 42: out of");
+//! ```
+//!
+//! The `Debug` and `Display` arguments implement those traits for the proxy by forwarding to the
+//! variable, so the proxy can be logged directly. (`derive(Debug)` would format the proxy itself,
+//! which has no fields.)
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(Debug, Display)]
+//! static mut BAUD_RATE: u32 = 115_200;
+//!
+//! let baud_rate = unsafe { BAUD_RATE::new() };
+//! assert_eq!(format!("{:?}", baud_rate), "115200");
+//! assert_eq!(format!("{}", baud_rate), "115200");
+//! ```
+//!
+//! The `Eq`, `Hash`, `Ord`, `PartialEq` and `PartialOrd` arguments do the same for comparisons
+//! and hashing.
+//!
+//! ```
+//! use std::collections::HashSet;
+//!
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(Eq, Hash, PartialEq)]
+//! static FIRMWARE_ID: u32 = 0xdead_beef;
+//!
+//! let mut seen = HashSet::new();
+//! assert!(seen.insert(FIRMWARE_ID::new()));
+//! assert!(!seen.insert(FIRMWARE_ID::new()));
+//! ```
+//!
+//! Proxies implement `AsRef`, `AsMut`, `Borrow` and `BorrowMut` so they can be passed to generic
+//! APIs that expect any of those traits.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut TX_BUF: [u8; 4] = [0; 4];
+//!
+//! fn fill(mut buf: impl AsMut<[u8; 4]>) {
+//!     buf.as_mut().copy_from_slice(b"ping");
+//! }
+//!
+//! let mut tx_buf = unsafe { TX_BUF::new() };
+//! fill(&mut tx_buf);
+//! fill(tx_buf);
+//! ```
+//!
+//! Likewise, proxies implement `Index` and `IndexMut` if the type of the variable does.
+//!
+//! ```
+//! use std::ops::Index;
+//!
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut SAMPLES: [u16; 4] = [0; 4];
+//!
+//! fn first<T: Index<usize, Output = u16>>(samples: &T) -> u16 {
+//!     samples[0]
+//! }
+//!
+//! let mut samples = unsafe { SAMPLES::new() };
+//! samples[0] = 512;
+//! assert_eq!(first(&samples), 512);
+//! ```
+//!
+//! Proxies are `'static` and implement `StableDeref` so, for `[u8; N]`, `[u16; N]` and other
+//! arrays of DMA words, they already implement `embedded_dma::ReadBuffer` and, if the variable is
+//! `static mut`, `WriteBuffer`, through the blanket implementations of the `embedded-dma` crate.
+//! HAL DMA APIs accept them as they are.
+//!
+//! ```
+//! extern crate embedded_dma;
+//!
+//! use embedded_dma::{ReadBuffer, WriteBuffer};
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut TX_BUF: [u8; 16] = [0; 16];
+//!
+//! #[Singleton]
+//! static mut RX_BUF: [u16; 8] = [0; 8];
+//!
+//! // e.g. a HAL's `Transfer::start`
+//! fn start<TX: ReadBuffer<Word = u8>, RX: WriteBuffer<Word = u16>>(tx: TX, mut rx: RX) {
+//!     let (_, tx_len) = unsafe { tx.read_buffer() };
+//!     let (_, rx_len) = unsafe { rx.write_buffer() };
+//!     assert_eq!((tx_len, rx_len), (16, 8));
+//! }
+//!
+//! start(unsafe { TX_BUF::new() }, unsafe { RX_BUF::new() });
+//! ```
+//!
+//! [`buffer::PingPong`](buffer/struct.PingPong.html) splits a `[[T; N]; 2]` singleton for double
+//! buffered DMA. The CPU works on one half while the other half, a `Half` handle, is lent to the
+//! hardware; `swap` exchanges them once the hardware is done.
+//!
+//! ```
+//! use owned_singleton::buffer::PingPong;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut ADC_BUF: [[u16; 4]; 2] = [[0; 4]; 2];
+//!
+//! let (mut cpu, mut dma) = PingPong::new(unsafe { ADC_BUF::new() });
+//!
+//! for round in 0..4 {
+//!     // e.g. the DMA transfer completes
+//!     dma.copy_from_slice(&[round; 4]);
+//!
+//!     dma = cpu.swap(dma);
+//!     assert_eq!(*cpu, [round; 4]);
+//!     assert_ne!(cpu.index(), dma.index());
+//! }
+//! ```
+//!
+//! The `split` method of `static mut` array proxies turns the proxy into
+//! [`buffer::Chunk`](buffer/struct.Chunk.html)s, each owning a disjoint, fixed size part of the
+//! array, e.g. to give each task its own slice. The number of chunks times their length must
+//! equal the length of the array; otherwise compilation fails.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut FRAMES: [u8; 64] = [0; 64];
+//!
+//! let [mut a, mut b, c, d] = unsafe { FRAMES::new() }.split::<16, 4>();
+//!
+//! a[0] = 1;
+//! b.copy_from_slice(&[2; 16]);
+//! assert_eq!(a.len(), 16);
+//! assert_eq!((c.index(), d.index()), (2, 3));
+//! assert_eq!(core::mem::size_of_val(&a), core::mem::size_of::<usize>());
+//! ```
+//!
+//! With the `bytemuck` feature `SingletonExt` also provides byte views of, and checked casts
+//! from, variables whose type implements `bytemuck::Pod`, e.g. to reuse one raw buffer for
+//! different layouts.
+//!
+//! ```
+//! # #[cfg(feature = "bytemuck")]
+//! # fn main() {
+//! use owned_singleton::{Singleton, SingletonExt};
+//!
+//! #[Singleton]
+//! static mut RAW: [u32; 2] = [0; 2];
+//!
+//! let mut raw = unsafe { RAW::new() };
+//! raw.as_bytes_mut()[0] = 1;
+//! assert_eq!(raw.as_bytes().len(), 8);
+//!
+//! let halves: &[u16] = raw.cast_slice().unwrap();
+//! assert_eq!(halves.len(), 4);
+//!
+//! // 8 bytes can't be split into 3 byte chunks
+//! assert!(raw.cast_slice::<[u8; 3]>().is_err());
+//! # }
+//! # #[cfg(not(feature = "bytemuck"))]
+//! # fn main() {}
+//! ```
+//!
+//! With the `serde` feature proxies implement `Serialize`, if the type of the variable does, and
+//! `SingletonExt::deserialize_into` overwrites the variable with a deserialized value, e.g. to
+//! update a configuration singleton from a host tool.
+//!
+//! ```
+//! # #[cfg(feature = "serde")]
+//! extern crate serde;
+//!
+//! # #[cfg(feature = "serde")]
+//! # fn main() {
+//! use owned_singleton::{Singleton, SingletonExt};
+//! use serde::de::{value::Error, IntoDeserializer};
+//! use serde::Serialize;
+//!
+//! #[Singleton]
+//! static mut BAUD_RATE: u32 = 9_600;
+//!
+//! fn snapshot<T: Serialize>(_config: &T) {}
+//!
+//! let mut baud_rate = unsafe { BAUD_RATE::new() };
+//! snapshot(&baud_rate);
+//!
+//! let update = IntoDeserializer::<Error>::into_deserializer(115_200u32);
+//! baud_rate.deserialize_into(update).unwrap();
+//! assert_eq!(*baud_rate, 115_200);
+//! # }
+//! # #[cfg(not(feature = "serde"))]
+//! # fn main() {}
+//! ```
+//!
+//! `Singleton` isn't object safe; the [`AnySingleton`](trait.AnySingleton.html) trait, also
+//! implemented for all singletons, is. It can be used to keep proxies of different types in the
+//! same collection.
+//!
+//! ```
+//! use owned_singleton::{AnySingleton, Singleton};
+//!
+//! #[Singleton]
+//! static mut RX_BUF: [u8; 128] = [0; 128];
+//!
+//! #[Singleton]
+//! static mut TX_BUF: [u8; 64] = [0; 64];
+//!
+//! let singletons: [Box<dyn AnySingleton>; 2] =
+//!     unsafe { [Box::new(RX_BUF::new()), Box::new(TX_BUF::new())] };
+//!
+//! let total: usize = singletons.iter().map(|s| s.size()).sum();
+//! assert_eq!(total, 192);
+//! assert_eq!(singletons[1].name(), "TX_BUF");
+//! ```
+//!
+//! A proxy can be `freeze`-d into a [`Shared`](struct.Shared.html) handle: a zero sized, `Copy`
+//! handle that only hands out shared references. Initialization code can fill in the variable
+//! through the proxy and then hand out read-only handles to the rest of the program.
+//!
+//! ```
+//! use owned_singleton::{Shared, Singleton};
+//!
+//! pub struct Config {
+//!     baud_rate: u32,
+//! }
+//!
+//! #[Singleton]
+//! static mut CONFIG: Config = Config { baud_rate: 0 };
+//!
+//! let mut config = unsafe { CONFIG::new() };
+//! config.baud_rate = 115_200;
+//!
+//! let shared: Shared<CONFIG> = config.freeze();
+//! let copy = shared;
+//! assert_eq!(shared.baud_rate, copy.baud_rate);
+//! ```
+//!
+//! The `refcount` argument lets the proxy be turned into any number of read-only `Ref` handles.
+//! Once all but one of the handles have been dropped, `Ref::upgrade` turns the last one back into
+//! the proxy, restoring exclusive access.
+//!
+//! ```
+//! use owned_singleton::{Ref, Singleton};
+//!
+//! #[Singleton(refcount)]
+//! static mut TABLE: [u8; 4] = [0; 4];
+//!
+//! let mut table = unsafe { TABLE::new() };
+//! table[0] = 1;
+//!
+//! let a = Ref::new(table);
+//! let b = a.clone();
+//! assert_eq!(Ref::count(&a), 2);
+//! assert_eq!(b[0], 1);
+//!
+//! // `b` is still alive
+//! let a = Ref::upgrade(a).err().unwrap();
+//! drop(b);
+//!
+//! let mut table = Ref::upgrade(a).ok().unwrap();
+//! table[0] = 2;
+//! ```
+//!
+//! With the `reset` argument the initializer of the variable is available as `initial()`; `reset`
+//! restores it, e.g. on a soft reboot or between tests.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(reset)]
+//! static mut RETRIES: u8 = 3;
+//!
+//! let mut retries = unsafe { RETRIES::new() };
+//! *retries -= 1;
+//!
+//! retries.reset();
+//! assert_eq!(*retries, RETRIES::initial());
+//! ```
+//!
+//! [`Lazy`](lazy/struct.Lazy.html) pairs the proxy of a `MaybeUninit<T>` variable with a closure
+//! that computes its value the first time it's dereferenced.
+//!
+//! ```
+//! use std::mem::MaybeUninit;
+//!
+//! use owned_singleton::lazy::Lazy;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut GREETING: MaybeUninit<String> = MaybeUninit::uninit();
+//!
+//! let name = "world";
+//! let mut greeting = Lazy::new(unsafe { GREETING::new() }, || format!("Hello, {}!", name));
+//! assert!(Lazy::get(&greeting).is_none());
+//!
+//! assert_eq!(*greeting, "Hello, world!");
+//! greeting.push_str("!!");
+//! assert_eq!(Lazy::get(&greeting).map(|s| s.len()), Some(15));
+//! ```
+//!
+//! [`OnceSingleton`](once/struct.OnceSingleton.html) turns the proxy of an `Option<T>` variable
+//! into a cell that's written once, at runtime, and then shared read-only.
+//!
+//! ```
+//! use owned_singleton::once::OnceSingleton;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut SERIAL_NUMBER: Option<u64> = None;
+//!
+//! let serial_number = OnceSingleton::new(unsafe { SERIAL_NUMBER::new() });
+//! assert_eq!(serial_number.get(), None);
+//!
+//! assert_eq!(*serial_number.get_or_init(|| 0x1234), 0x1234);
+//! assert_eq!(serial_number.set(0x5678), Err(0x5678));
+//! assert_eq!(serial_number.get(), Some(&0x1234));
+//! ```
+//!
+//! [`BorrowSingleton`](cell/struct.BorrowSingleton.html) keeps the proxy and checks borrows of the
+//! variable at runtime, like `RefCell` does, so it can be shared between several call sites.
+//!
+//! ```
+//! use owned_singleton::cell::BorrowSingleton;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut LOG: [u8; 4] = [0; 4];
+//!
+//! let log = BorrowSingleton::new(unsafe { LOG::new() });
+//!
+//! log.borrow_mut()[0] = 1;
+//!
+//! let a = log.borrow();
+//! let b = log.borrow();
+//! assert_eq!(a[0] + b[0], 2);
+//! assert!(log.try_borrow_mut().is_none());
+//! ```
+//!
+//! With the `critical-section` feature, [`sync::Mutex`](sync/struct.Mutex.html) shares a
+//! singleton between thread mode and interrupt handlers; locking it disables interrupts.
+//!
+//! ```
+//! # #[cfg(feature = "critical-section")]
+//! # fn main() {
+//! use owned_singleton::sync::Mutex;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(Send)]
+//! static mut EVENTS: u32 = 0;
+//!
+//! let events = Mutex::new(unsafe { EVENTS::new() });
+//!
+//! // e.g. from an interrupt handler
+//! *events.lock().unwrap() += 1;
+//!
+//! assert_eq!(events.with_lock(|events| *events), 1);
+//! # }
+//! # #[cfg(not(feature = "critical-section"))]
+//! # fn main() {}
+//! ```
+//!
+//! When only some interrupt handlers share the singleton,
+//! [`ceiling::Ceiling`](ceiling/struct.Ceiling.html) masks the interrupts up to a priority
+//! ceiling, rather than all of them, while the variable is locked.
+//!
+//! [`sync::RwLock`](sync/struct.RwLock.html) lets several readers, e.g. telemetry tasks, access a
+//! singleton at the same time while writers, e.g. configuration updates, take exclusive access.
+//!
+//! ```
+//! use owned_singleton::sync::RwLock;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(Send, Sync)]
+//! static mut SAMPLE_RATE: u32 = 1_000;
+//!
+//! let sample_rate = RwLock::new(unsafe { SAMPLE_RATE::new() });
+//!
+//! {
+//!     let a = sample_rate.read().unwrap();
+//!     let b = sample_rate.read().unwrap();
+//!     assert_eq!(*a + *b, 2_000);
+//!
+//!     // readers keep writers out
+//!     assert!(sample_rate.try_write().is_err());
+//! }
+//!
+//! *sample_rate.write().unwrap() = 2_000;
+//! assert_eq!(*sample_rate.read().unwrap(), 2_000);
+//! ```
+//!
+//! [`spsc::Queue`](spsc/struct.Queue.html) is a lock-free single producer single consumer queue
+//! whose storage is a singleton. [`spsc::split`](spsc/fn.split.html) turns its proxy into zero
+//! sized `Producer` and `Consumer` handles, e.g. to move data from an interrupt handler to thread
+//! mode.
+//!
+//! ```
+//! use owned_singleton::spsc::{self, Queue};
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut RX: Queue<u8, 4> = Queue::new();
+//!
+//! let (mut producer, mut consumer) = spsc::split(unsafe { RX::new() });
+//! assert_eq!(core::mem::size_of_val(&producer), 0);
+//!
+//! // e.g. from the UART interrupt handler
+//! for byte in b"ping" {
+//!     producer.enqueue(*byte).unwrap();
+//! }
+//! assert_eq!(producer.enqueue(b'!'), Err(b'!'));
+//!
+//! // e.g. from thread mode
+//! assert_eq!(consumer.peek(), Some(&b'p'));
+//! assert_eq!(consumer.dequeue(), Some(b'p'));
+//! assert_eq!(consumer.len(), 3);
+//! ```
+//!
+//! [`pool::Pool`](pool/struct.Pool.html) allocates values of a single type from a singleton. Its
+//! `Box`es are one byte handles that return their slot to the pool when dropped.
+//!
+//! ```
+//! use owned_singleton::pool::{Pool, Storage};
+//! use owned_singleton::Singleton;
+//!
+//! struct Packet {
+//!     len: usize,
+//!     data: [u8; 64],
+//! }
+//!
+//! #[Singleton]
+//! static mut PACKETS: Storage<Packet, 2> = Storage::new();
+//!
+//! let pool = Pool::new(unsafe { PACKETS::new() });
+//! assert_eq!(core::mem::size_of::<owned_singleton::pool::Box<PACKETS>>(), 1);
+//!
+//! let mut a = pool.alloc(Packet { len: 0, data: [0; 64] }).ok().unwrap();
+//! let b = pool.alloc(Packet { len: 4, data: [1; 64] }).ok().unwrap();
+//! assert!(pool.alloc(Packet { len: 0, data: [0; 64] }).is_err());
+//!
+//! a.len = b.len;
+//! assert_eq!(a.len, 4);
+//!
+//! // dropping a box frees its slot
+//! drop(b);
+//! assert!(pool.alloc(Packet { len: 0, data: [0; 64] }).is_ok());
+//! ```
+//!
+//! [`alloc::Bump`](alloc/struct.Bump.html) carves allocations of any type out of a `[u8; N]`
+//! singleton, e.g. as per-frame or per-request scratch memory; `reset` frees them all at once.
+//!
+//! ```
+//! use owned_singleton::alloc::Bump;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut SCRATCH: [u8; 64] = [0; 64];
+//!
+//! let mut scratch = Bump::new(unsafe { SCRATCH::new() });
+//!
+//! for _frame in 0..3 {
+//!     let header = scratch.alloc(0xdead_beef_u32).unwrap();
+//!     let payload = scratch.alloc_slice_copy(b"hello").unwrap();
+//!     payload[0] = b'H';
+//!
+//!     assert_eq!(*header, 0xdead_beef);
+//!     assert_eq!(payload, b"Hello");
+//!     assert!(scratch.alloc([0u8; 64]).is_err());
+//!
+//!     scratch.reset();
+//! }
+//! ```
+//!
+//! [`alloc::Heap`](alloc/struct.Heap.html) turns a `[u8; N]` singleton into a global allocator
+//! for programs that want `alloc` support without an external allocator crate.
+//!
+//! ```
+//! use std::alloc::{GlobalAlloc, Layout};
+//!
+//! use owned_singleton::alloc::Heap;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut HEAP_MEMORY: [u8; 1024] = [0; 1024];
+//!
+//! // register it with `#[global_allocator]`
+//! static HEAP: Heap<HEAP_MEMORY> = Heap::new();
+//!
+//! HEAP.init(unsafe { HEAP_MEMORY::new() });
+//!
+//! unsafe {
+//!     let layout = Layout::new::<[u8; 16]>();
+//!     let bytes = HEAP.alloc(layout);
+//!     assert!(!bytes.is_null());
+//!
+//!     // the most recent allocation is reclaimed when freed
+//!     HEAP.dealloc(bytes, layout);
+//!     assert_eq!(HEAP.used(), 0);
+//!
+//!     let words = HEAP.alloc(Layout::new::<[u32; 4]>());
+//!     assert_eq!(words as usize % 4, 0);
+//!
+//!     assert!(HEAP.alloc(Layout::new::<[u8; 2048]>()).is_null());
+//! }
+//! ```
+//!
+//! With the `allocator-api` feature, which requires a nightly compiler, `&alloc::Bump` and
+//! `&pool::Pool` implement `core::alloc::Allocator`, so collections can keep their storage in
+//! singletons.
+//!
+//! ```
+//! # #![cfg_attr(feature = "allocator-api", feature(allocator_api))]
+//! # #[cfg(feature = "allocator-api")]
+//! # fn main() {
+//! use owned_singleton::alloc::Bump;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut SCRATCH: [u8; 256] = [0; 256];
+//!
+//! let scratch = Bump::new(unsafe { SCRATCH::new() });
+//!
+//! let mut samples = Vec::with_capacity_in(16, &scratch);
+//! samples.extend_from_slice(&[1_i16, -1, 2, -2]);
+//! assert_eq!(samples.iter().sum::<i16>(), 0);
+//! assert!(scratch.used() >= 32);
+//! # }
+//! # #[cfg(not(feature = "allocator-api"))]
+//! # fn main() {}
+//! ```
+//!
+//! A `Token` is a zero sized, `Copy` proof that a singleton has been created. It doesn't grant
+//! access to the variable so it can be handed out freely, e.g. to APIs that must only be used
+//! after the singleton has been initialized.
+//!
+//! ```
+//! use owned_singleton::{Singleton, Token};
+//!
+//! #[Singleton]
+//! static mut CLOCKS: u32 = 0;
+//!
+//! // can only be called once the clocks have been configured
+//! fn delay_ms(_clocks: Token<CLOCKS>, _ms: u32) {}
+//!
+//! let mut clocks = unsafe { CLOCKS::new() };
+//! *clocks = 8_000_000;
+//!
+//! let token = clocks.token();
+//! delay_ms(token, 1);
+//! delay_ms(token, 2);
+//! ```
+//!
+//! With the `std` feature, [`testing::MockSingleton<T>`](testing/struct.MockSingleton.html)
+//! implements `SingletonMut<Type = T>` on top of a thread-local value, so drivers written against
+//! a `SingletonMut` bound can be unit tested on the host without declaring a `static mut`
+//! variable per test.
+//!
+//! ```
+//! # #[cfg(feature = "std")]
+//! # fn main() {
+//! use owned_singleton::testing::MockSingleton;
+//! use owned_singleton::SingletonMut;
+//!
+//! fn record<S>(mut log: S, sample: u8) -> S
+//! where
+//!     S: SingletonMut<Type = Vec<u8>>,
+//! {
+//!     log.push(sample);
+//!     log
+//! }
+//!
+//! let log = record(MockSingleton::new(vec![1, 2]), 3);
+//! assert_eq!(log.into_inner(), [1, 2, 3]);
+//! # }
+//! # #[cfg(not(feature = "std"))]
+//! # fn main() {}
+//! ```
+//!
+//! `static mut` variables never move so they can hold self-referential or intrusive data
+//! structures. The `pinned` argument replaces the `DerefMut` implementation with `as_pin_mut` and
+//! `unwrap_pinned` methods that return pinned references to the variable; the proxy itself
+//! doesn't implement `Unpin`.
+//!
+//! ```
+//! use std::marker::PhantomPinned;
+//! use std::pin::Pin;
+//!
+//! use owned_singleton::Singleton;
+//!
+//! pub struct Node {
+//!     next: *const Node,
+//!     _pinned: PhantomPinned,
+//! }
+//!
+//! fn link(node: Pin<&mut Node>) {
+//!     unsafe {
+//!         let node = node.get_unchecked_mut();
+//!         node.next = node;
+//!     }
+//! }
+//!
+//! #[Singleton(pinned)]
+//! static mut HEAD: Node = Node { next: 0 as *const Node, _pinned: PhantomPinned };
+//!
+//! let mut head = unsafe { HEAD::new() };
+//! link(head.as_pin_mut());
+//! assert_eq!(head.next, &*head as *const Node);
+//! ```
+//!
 //! The `Singleton` attribute doesn't implement the `Send` or `Sync` traits by default; this results
 //! in a proxy struct that does *not* implement `Send` or `Sync`. To opt into the `Send` and `Sync`
 //! traits add the `Send` and `Sync` arguments to the `Singleton` attribute.
@@ -49,9 +1480,331 @@
 //! is_sync::<FOO>();
 //! ```
 //!
-//! Using `Singleton` on a `static` variable results in `DerefMut` not being implemented for the
-//! proxy struct. However, the proxy struct will still be a handle to a `static mut` variable so
-//! there's *no* `Sync` requirement on the type of the `static mut` variable.
+//! With the `take` Cargo feature enabled the `Singleton` attribute also generates a `take`
+//! constructor that can be used to safely obtain the proxy. `take` returns `Some` only the first
+//! time it's called; `unsafe fn new` is still available when the runtime check is not wanted.
+//!
+//! ```
+//! # #[cfg(feature = "take")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut FOO: u32 = 0;
+//!
+//! let mut foo = FOO::take().unwrap();
+//! *foo += 1;
+//!
+//! assert!(FOO::take().is_none());
+//! # }
+//! # #[cfg(not(feature = "take"))]
+//! # fn main() {}
+//! ```
+//!
+//! The `take` flag is implemented using atomic compare and swap operations. Targets that lack
+//! these operations (e.g. ARMv6-M, MSP430) need one of two features:
+//!
+//! - `critical-section`: the flag, and every other runtime flag and counter of the crate, is
+//!   implemented using [`critical-section`] instead, so the application must provide a critical
+//!   section implementation. The `cs-take` feature selects this implementation on all targets.
+//!
+//! - `portable-atomic`: the runtime flags are implemented on top of the [`portable-atomic`] crate,
+//!   which provides fallback implementations for targets that lack native atomics (see its
+//!   documentation for the available options, e.g. the `unsafe-assume-single-core` feature).
+//!   This takes precedence over the `critical-section` feature, but not over `cs-take`.
+//!
+//! [`critical-section`]: https://crates.io/crates/critical-section
+//! [`portable-atomic`]: https://crates.io/crates/portable-atomic
+//!
+//! Add the `release` argument to make the singleton available to `take` again once the proxy is
+//! dropped.
+//!
+//! ```
+//! # #[cfg(feature = "take")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(release)]
+//! static mut FOO: u32 = 0;
+//!
+//! let foo = FOO::take().unwrap();
+//! assert!(FOO::take().is_none());
+//!
+//! drop(foo);
+//! assert!(FOO::take().is_some());
+//! # }
+//! # #[cfg(not(feature = "take"))]
+//! # fn main() {}
+//! ```
+//!
+//! The `track-owner` feature records where each singleton was taken. `FOO::taken_at` returns the
+//! location of the `take` call and `FOO::try_take` returns a [`TakeError`](struct.TakeError.html)
+//! that reports it, so a failed `take` points at the code that holds the singleton.
+//!
+//! ```
+//! # #[cfg(feature = "track-owner")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut SPI: u32 = 0;
+//!
+//! let spi = SPI::take().unwrap();
+//! let line = line!() - 1;
+//! assert_eq!(SPI::taken_at().unwrap().line(), line);
+//!
+//! let e = SPI::try_take().err().unwrap();
+//! assert_eq!(e.taken_at(), SPI::taken_at());
+//! // e.g. "`SPI` has already been taken at src/main.rs:9:11"
+//! assert!(e.to_string().starts_with("`SPI` has already been taken at "));
+//! # drop(spi);
+//! # }
+//! # #[cfg(not(feature = "track-owner"))]
+//! # fn main() {}
+//! ```
+//!
+//! The elements of `static mut` arrays can also be taken one by one, with `take_nth`, so
+//! independent subsystems can each claim a slot of a shared table. Taking an element keeps the
+//! whole array from being taken, and vice versa. With the `release` argument dropping an element
+//! makes it available again.
+//!
+//! ```
+//! # #[cfg(feature = "take")]
+//! # fn main() {
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(release)]
+//! static mut CHANNELS: [u32; 4] = [0; 4];
+//!
+//! let mut timer = CHANNELS::take_nth(0).unwrap();
+//! let adc = CHANNELS::take_nth(1).unwrap();
+//! *timer = 1_000;
+//!
+//! assert!(CHANNELS::take_nth(0).is_none());
+//! assert!(CHANNELS::take_nth(4).is_none());
+//! assert!(CHANNELS::take().is_none());
+//!
+//! drop((timer, adc));
+//! let channels = CHANNELS::take().unwrap();
+//! assert_eq!(channels[0], 1_000);
+//! assert!(CHANNELS::take_nth(2).is_none());
+//! # }
+//! # #[cfg(not(feature = "take"))]
+//! # fn main() {}
+//! ```
+//!
+//! The `on_drop` argument runs a function on the `static mut` variable when the proxy is
+//! dropped. Drivers can use it to put the peripheral back into a known state on teardown.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! pub struct Uart {
+//!     enabled: bool,
+//! }
+//!
+//! fn shutdown(uart: &mut Uart) {
+//!     uart.enabled = false;
+//! }
+//!
+//! #[Singleton(on_drop = shutdown)]
+//! static mut UART: Uart = Uart { enabled: false };
+//!
+//! let mut uart = unsafe { UART::new() };
+//! uart.enabled = true;
+//! drop(uart);
+//!
+//! let uart = unsafe { UART::new() };
+//! assert!(!uart.enabled);
+//! ```
+//!
+//! The `zeroize` argument wipes the `static mut` variable when the proxy is dropped, which is
+//! useful for key material. The all-zeros bit pattern must be a valid value of the variable's
+//! type. Enable the `zeroize` feature to also get an implementation of the [`Zeroize`] trait for
+//! proxies whose variable type implements it.
+//!
+//! [`Zeroize`]: https://docs.rs/zeroize
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton(zeroize)]
+//! static mut KEY: [u8; 16] = [0; 16];
+//!
+//! let mut key = unsafe { KEY::new() };
+//! key.copy_from_slice(&[0xaa; 16]);
+//! drop(key);
+//!
+//! let key = unsafe { KEY::new() };
+//! assert_eq!(*key, [0; 16]);
+//! ```
+//!
+//! `Send` and `Sync` are only implemented for the proxy if the type of the `static mut` variable
+//! implements them. `Send(force)` and `Sync(force)` lift this requirement; it's then *your*
+//! responsibility to ensure that moving or sharing the proxy across threads is sound.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! struct Descriptor {
+//!     // only ever dereferenced by the owner of the proxy
+//!     buffer: *mut u8,
+//! }
+//!
+//! #[Singleton(Send(force))]
+//! static mut DESCRIPTOR: Descriptor = Descriptor {
+//!     buffer: 0 as *mut u8,
+//! };
+//!
+//! fn is_send<T>() where T: Send {}
+//!
+//! is_send::<DESCRIPTOR>();
+//! ```
+//!
+//! The attribute can also be used on `static mut` variables declared inside a function. The proxy
+//! type is then local to the function so the function has full control over how the storage is
+//! handed out.
+//!
+//! ```
+//! use std::ops::DerefMut;
+//!
+//! use owned_singleton::Singleton;
+//!
+//! /// # Safety
+//! ///
+//! /// This function must be called at most once
+//! unsafe fn rx_buffer() -> impl DerefMut<Target = [u8; 64]> {
+//!     #[Singleton]
+//!     static mut BUFFER: [u8; 64] = [0; 64];
+//!
+//!     BUFFER::new()
+//! }
+//!
+//! let mut buffer = unsafe { rx_buffer() };
+//! buffer[0] = 1;
+//! ```
+//!
+//! The `Singletons` attribute turns all the `static mut` variables in a module into owned
+//! singletons and adds a `Resources` struct that groups their proxies. With the `take` feature
+//! enabled `Resources::take` hands out all the proxies at once, which is handy for an `init`
+//! function that returns every resource the application needs.
+//!
+//! ```
+//! # #[cfg(feature = "take")]
+//! # fn main() {
+//! use owned_singleton::Singletons;
+//!
+//! #[Singletons]
+//! mod resources {
+//!     pub static mut RX_BUF: [u8; 64] = [0; 64];
+//!
+//!     #[Singleton(Send)]
+//!     pub static mut COUNTER: u32 = 0;
+//! }
+//!
+//! let resources::Resources { mut rx_buf, mut counter } = resources::Resources::take().unwrap();
+//! rx_buf[0] = 1;
+//! *counter += 1;
+//!
+//! assert!(resources::Resources::take().is_none());
+//! assert!(resources::COUNTER::take().is_none());
+//! # }
+//! # #[cfg(not(feature = "take"))]
+//! # fn main() {}
+//! ```
+//!
+//! `Resources::take` hands out all the proxies or none: if one of the singletons has already been
+//! taken the ones it took are given back.
+//!
+//! ```
+//! # #[cfg(feature = "take")]
+//! # fn main() {
+//! use owned_singleton::Singletons;
+//!
+//! #[Singletons]
+//! mod resources {
+//!     pub static mut RX_BUF: [u8; 64] = [0; 64];
+//!     pub static mut TX_BUF: [u8; 64] = [0; 64];
+//! }
+//!
+//! let tx_buf = resources::TX_BUF::take().unwrap();
+//! assert!(resources::Resources::take().is_none());
+//!
+//! // `RX_BUF` can still be taken
+//! assert!(resources::RX_BUF::take().is_some());
+//! # drop(tx_buf);
+//! # }
+//! # #[cfg(not(feature = "take"))]
+//! # fn main() {}
+//! ```
+//!
+//! Each proxy of the group is created by `Resources`, so arguments that change how the proxy is
+//! created, like `init`, `typestate` or `volatile`, are rejected inside the module.
+//!
+//! ```compile_fail
+//! use owned_singleton::Singletons;
+//!
+//! #[Singletons]
+//! mod registers {
+//!     // error: `volatile` can't be used inside a `#[Singletons]` module
+//!     #[Singleton(volatile)]
+//!     pub static mut STATUS: u32 = 0;
+//! }
+//! ```
+//!
+//! Singletons that are declared in different modules, or crates, can be grouped the same way with
+//! the [`resources!`](macro.resources.html) macro.
+//!
+//! Storage defined outside Rust (e.g. in C code, assembly or a linker script) can be wrapped as
+//! well: apply the attribute to an `extern` block and each `static` variable declared in it gets a
+//! proxy. The variable keeps linking against its original symbol.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! # mod c {
+//! #     #[no_mangle]
+//! #     static mut C_STATE: u32 = 42;
+//! # }
+//! #[Singleton]
+//! extern "C" {
+//!     // defined in C: `uint32_t C_STATE = 42;`
+//!     static mut C_STATE: u32;
+//! }
+//!
+//! let mut state = unsafe { C_STATE::new() };
+//! assert_eq!(*state, 42);
+//! *state += 1;
+//! ```
+//!
+//! With the `registry` Cargo feature enabled each singleton also places a
+//! [`Descriptor`](registry/struct.Descriptor.html) (name, address, size, alignment and linker
+//! section of its storage) in the `owned_singleton_registry` linker section, which memory
+//! reporting and diagnostic tools can inspect. This requires a target that uses the ELF object
+//! format. `registry::iter` walks the section at runtime, e.g. to print a memory inventory at boot.
+//!
+//! ```
+//! # #[cfg(feature = "registry")]
+//! # fn main() {
+//! use owned_singleton::{registry, Singleton};
+//!
+//! #[Singleton]
+//! static mut RX_BUF: [u8; 64] = [0; 64];
+//!
+//! for singleton in registry::iter() {
+//!     println!("{} @ {:?}: {} bytes", singleton.name, singleton.address, singleton.size);
+//! }
+//!
+//! assert!(registry::iter().any(|singleton| singleton.name.ends_with("RX_BUF")));
+//! # }
+//! # #[cfg(not(feature = "registry"))]
+//! # fn main() {}
+//! ```
+//!
+//! Using `Singleton` on a `static` variable results in `DerefMut` and `SingletonMut` not being
+//! implemented for the proxy struct. The variable stays a plain `static` so the linker can place
+//! it in read-only memory (e.g. Flash) but, as it's only reachable through the proxy, there's *no*
+//! `Sync` requirement on its type.
 //!
 //! ```
 //! use std::marker::PhantomData;
@@ -62,28 +1815,162 @@
 //! #[Singleton]
 //! static FOO: PhantomData<*const ()> = PhantomData;
 //! ```
+//!
+//! Such proxies can't be `unwrap`-ped into a `&'static mut` reference; use `unwrap_shared` to get
+//! a `&'static` reference instead. As they only hand out shared references they also implement
+//! `Clone` and `Copy`, and can be created with a safe `new` constructor, unless the `release` or
+//! `lazy` arguments are used; `Send` then requires the type of the variable to implement `Sync`.
+//!
+//! ```
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static CONFIG: [u8; 4] = *b"v1.0";
+//!
+//! let config = CONFIG::new();
+//! let copy = config;
+//! assert_eq!(*config, *copy);
+//!
+//! let config: &'static [u8; 4] = config.unwrap_shared();
+//! assert_eq!(config, b"v1.0");
+//! ```
 
 #![deny(missing_docs)]
 #![deny(warnings)]
+#![cfg_attr(feature = "allocator-api", feature(allocator_api))]
 #![no_std]
 
+#[cfg(feature = "bytemuck")]
+extern crate bytemuck;
+#[cfg(feature = "critical-section")]
+extern crate critical_section;
+#[cfg(feature = "defmt")]
+extern crate defmt;
+#[cfg(feature = "log")]
+extern crate log;
+#[cfg(all(loom, test))]
+extern crate loom;
+#[cfg(feature = "macros")]
 extern crate owned_singleton_macros;
+#[cfg(feature = "portable-atomic")]
+extern crate portable_atomic;
+#[cfg(feature = "rtic")]
+extern crate rtic_core;
+#[cfg(feature = "serde")]
+extern crate serde;
 extern crate stable_deref_trait;
+#[cfg(feature = "std")]
+extern crate std;
+#[cfg(feature = "ufmt")]
+extern crate ufmt;
+#[cfg(feature = "zeroize")]
+extern crate zeroize;
+
+#[cfg(all(
+    not(target_has_atomic = "8"),
+    not(feature = "critical-section"),
+    not(feature = "portable-atomic")
+))]
+compile_error!(
+    "this target lacks compare and swap; enable the `critical-section` or the `portable-atomic` \
+     feature"
+);
 
-pub use owned_singleton_macros::Singleton;
+use core::{mem, ops::DerefMut, ptr::NonNull};
+
+#[cfg(feature = "macros")]
+pub use owned_singleton_macros::{Singleton, Singletons};
 use stable_deref_trait::StableDeref;
 
+#[macro_use]
+mod macros;
+
+pub mod alloc;
+mod any;
+mod atomic;
+pub mod buffer;
+pub mod ceiling;
+pub mod cell;
+#[cfg(feature = "defmt-logger")]
+pub mod defmt_logger;
 #[doc(hidden)]
 pub mod export;
+mod ext;
+#[cfg(feature = "take")]
+mod flag;
+pub mod lazy;
+pub mod mmio;
+pub mod once;
+#[cfg(feature = "track-owner")]
+mod owner;
+pub mod panic_persist;
+pub mod pool;
+mod refcount;
+#[cfg(feature = "registry")]
+pub mod registry;
+mod shared;
+pub mod spsc;
+pub mod sync;
+pub mod tagged;
+#[cfg(feature = "std")]
+pub mod testing;
+mod token;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod typestate;
+mod writer;
+
+pub use any::AnySingleton;
+pub use ext::SingletonExt;
+#[cfg(feature = "track-owner")]
+pub use owner::TakeError;
+pub use refcount::{Ref, RefCounted};
+pub use shared::Shared;
+pub use token::Token;
+pub use writer::Writer;
+
+/// Rejects handles that could be sent to another core when `S` is a `per_cpu` singleton
+struct NotPerCpu<S>(core::marker::PhantomData<S>);
+
+impl<S> NotPerCpu<S>
+where
+    S: Singleton,
+{
+    const CHECK: () = assert!(
+        !S::PER_CPU,
+        "`per_cpu` singletons can't be frozen or turned into tokens"
+    );
+}
 
 /// An owned singleton: a proxy (handle) for a `static mut` variable
+///
+/// # Safety
+///
+/// Implementers must guarantee that `get` always returns a non-null pointer to the same
+/// `static mut` variable and that `Deref` / `DerefMut` hand out references to that variable
 pub unsafe trait Singleton: StableDeref {
     /// The type of the `static mut` variable behind this proxy
     type Type;
 
+    /// The name of this proxy
+    ///
+    /// Empty unless the implementation provides one; the `Singleton` attribute and the macros of
+    /// this crate always do
+    const NAME: &'static str = "";
+
+    /// The size, in bytes, of the variable behind this proxy
+    const SIZE: usize = mem::size_of::<Self::Type>();
+
+    /// The alignment, in bytes, of the variable behind this proxy
+    const ALIGN: usize = mem::align_of::<Self::Type>();
+
+    /// Whether `get` returns the copy of the variable that belongs to the current core
+    #[doc(hidden)]
+    const PER_CPU: bool = false;
+
     /// Creates a new singleton
     ///
-    /// # Unsafety
+    /// # Safety
     ///
     /// It's UB to create more than one instance of this singleton
     unsafe fn new() -> Self;
@@ -91,11 +1978,108 @@ pub unsafe trait Singleton: StableDeref {
     /// Returns a pointer to the `static mut` variable behind this proxy
     fn get() -> *mut Self::Type;
 
+    /// Returns a non-null pointer to the `static mut` variable behind this proxy
+    fn get_nonnull() -> NonNull<Self::Type> {
+        // NOTE `get` returns the address of a `static` variable, which is never null
+        unsafe { NonNull::new_unchecked(Self::get()) }
+    }
+
+    /// Returns a `&'static mut` reference to the variable behind this proxy without owning it
+    ///
+    /// This is meant for code that can't reach the proxy, like panic and exception handlers.
+    ///
+    /// # Safety
+    ///
+    /// The returned reference must not coexist with the proxy or any other reference to the
+    /// variable
+    unsafe fn steal() -> &'static mut Self::Type
+    where
+        Self: SingletonMut,
+    {
+        &mut *Self::get()
+    }
+
+    /// Consumes this singleton and returns a read-only handle to the variable behind it
+    ///
+    /// The handle can be freely copied but it can't be turned back into a proxy.
+    fn freeze(self) -> Shared<Self>
+    where
+        Self: Sized,
+    {
+        // dereferencing makes sure the variable is initialized (e.g. `lazy` singletons)
+        let _ = &*self;
+        // don't run the destructor, if any; it may make the singleton available again
+        mem::forget(self);
+        unsafe { Shared::new() }
+    }
+
+    /// Returns a token that proves that this singleton has been created
+    fn token(&self) -> Token<Self>
+    where
+        Self: Sized,
+    {
+        Token::new(self)
+    }
+
     /// Consumes this singleton and returns a `&'static mut` reference to the variable behind it
+    ///
+    /// Only proxies that implement `SingletonMut` can be unwrapped this way; see `unwrap_shared`
     fn unwrap(self) -> &'static mut Self::Type
     where
-        Self: Sized,
+        Self: Sized + SingletonMut,
     {
+        // don't run the destructor, if any; it may make the singleton available again
+        mem::forget(self);
         unsafe { &mut *Self::get() }
     }
+
+    /// Leaks this singleton; returns a `&'static mut` reference to the variable behind it
+    ///
+    /// This is the same as `unwrap`; the proxies also implement `From<Self> for &'static mut
+    /// Self::Type`, which does the same. Use either to pass the variable to APIs that take
+    /// `&'static mut` references, e.g. the constructors of DMA transfers.
+    fn leak(self) -> &'static mut Self::Type
+    where
+        Self: Sized + SingletonMut,
+    {
+        self.unwrap()
+    }
+
+    /// Consumes this singleton and returns a `&'static` reference to the variable behind it
+    fn unwrap_shared(self) -> &'static Self::Type
+    where
+        Self: Sized,
+    {
+        // don't run the destructor, if any; it may make the singleton available again
+        mem::forget(self);
+        unsafe { &*Self::get() }
+    }
+}
+
+/// An owned singleton whose variable can be mutated through the proxy
+///
+/// The `Singleton` attribute implements this trait for the proxies of `static mut` variables.
+///
+/// # Safety
+///
+/// The variable behind the proxy must be mutable, i.e. it must not live in read-only memory
+pub unsafe trait SingletonMut:
+    Singleton + DerefMut<Target = <Self as Singleton>::Type>
+{
+}
+
+/// An owned singleton whose address is known at compile time
+///
+/// With the `const-ptr` feature the `Singleton` attribute implements this trait for all proxies
+/// except the ones of `lazy` and `per_cpu` variables.
+///
+/// # Safety
+///
+/// `PTR` must be equal to the pointer returned by `Singleton::get`
+#[cfg(feature = "const-ptr")]
+pub unsafe trait ConstSingleton: Singleton {
+    /// Pointer to the variable behind this proxy
+    ///
+    /// Unlike `Singleton::get` this can be used to initialize other `static` variables.
+    const PTR: *mut Self::Type;
 }
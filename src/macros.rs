@@ -0,0 +1,545 @@
+//! `macro_rules!` macros
+
+/// Fails the build if the combined size of the given singletons exceeds a budget
+///
+/// The first argument names the memory region the budget applies to and gives its size in bytes;
+/// it's followed by the list of singletons (proxy types) that live in that region.
+///
+/// ```
+/// use owned_singleton::{budget, Singleton};
+///
+/// #[Singleton]
+/// static mut RX_BUF: [u8; 1024] = [0; 1024];
+///
+/// #[Singleton]
+/// static mut TX_BUF: [u8; 1024] = [0; 1024];
+///
+/// budget!(RAM = 4 * 1024; RX_BUF, TX_BUF);
+/// # fn main() {}
+/// ```
+///
+/// ```compile_fail
+/// use owned_singleton::{budget, Singleton};
+///
+/// #[Singleton]
+/// static mut RX_BUF: [u8; 4096] = [0; 4096];
+///
+/// // error: the singletons exceed the `RAM` budget
+/// budget!(RAM = 2 * 1024; RX_BUF);
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! budget {
+    ($region:ident = $budget:expr; $($singleton:ty),+ $(,)*) => {
+        const _: () = assert!(
+            0 $(+ <$singleton as $crate::Singleton>::SIZE)+ <= $budget,
+            concat!("the singletons exceed the `", stringify!($region), "` budget")
+        );
+    };
+}
+
+/// Registers a [`defmt_logger::Logger`](defmt_logger/struct.Logger.html) as the
+/// `#[defmt::global_logger]`
+///
+/// The argument is the path to a `static` variable of type `Logger<S>`. The crate that invokes the
+/// macro must depend on `defmt`.
+///
+/// ```
+/// # #[cfg(feature = "defmt-logger")]
+/// # mod app {
+/// use owned_singleton::defmt_logger::Logger;
+/// use owned_singleton::{global_logger, Singleton};
+///
+/// #[Singleton]
+/// static mut LOG_BUF: [u8; 256] = [0; 256];
+///
+/// static LOGGER: Logger<LOG_BUF> = Logger::new();
+///
+/// global_logger!(LOGGER);
+///
+/// pub fn init() {
+///     LOGGER.init(unsafe { LOG_BUF::new() });
+/// }
+///
+/// pub fn drain(buf: &mut [u8]) -> usize {
+///     LOGGER.read(buf)
+/// }
+/// # }
+/// # fn main() {}
+/// ```
+#[cfg(feature = "defmt-logger")]
+#[macro_export]
+macro_rules! global_logger {
+    ($logger:path) => {
+        #[defmt::global_logger]
+        struct __OwnedSingletonLogger;
+
+        unsafe impl defmt::Logger for __OwnedSingletonLogger {
+            fn acquire() {
+                $logger.acquire()
+            }
+
+            unsafe fn flush() {
+                $logger.flush()
+            }
+
+            unsafe fn release() {
+                $logger.release()
+            }
+
+            unsafe fn write(bytes: &[u8]) {
+                $logger.write(bytes)
+            }
+        }
+    };
+}
+
+/// Groups already declared singletons into a struct that creates, or takes, all of them at once
+///
+/// Each field names a singleton (proxy type). The struct gets an unsafe `new` constructor and,
+/// with the `take` feature, a checked `take` constructor that hands out all the proxies or none.
+///
+/// ```
+/// # #[cfg(feature = "take")]
+/// # fn main() {
+/// use owned_singleton::resources;
+///
+/// mod serial {
+///     use owned_singleton::Singleton;
+///
+///     #[Singleton]
+///     pub static mut RX_BUF: [u8; 64] = [0; 64];
+/// }
+///
+/// mod app {
+///     use owned_singleton::Singleton;
+///
+///     #[Singleton]
+///     pub static mut STATE: u32 = 0;
+/// }
+///
+/// resources! {
+///     pub struct Resources {
+///         pub rx_buf: serial::RX_BUF,
+///         pub state: app::STATE,
+///     }
+/// }
+///
+/// let Resources { mut rx_buf, mut state } = Resources::take().unwrap();
+/// rx_buf[0] = 1;
+/// *state += 1;
+///
+/// assert!(Resources::take().is_none());
+/// # }
+/// # #[cfg(not(feature = "take"))]
+/// # fn main() {}
+/// ```
+///
+/// If one of the singletons has already been taken `take` gives back the ones it took.
+///
+/// ```
+/// # #[cfg(feature = "take")]
+/// # fn main() {
+/// use owned_singleton::{declare_singleton, resources};
+///
+/// declare_singleton! {
+///     pub static mut RX_BUF: [u8; 64] = [0; 64];
+/// }
+///
+/// declare_singleton! {
+///     pub static mut TX_BUF: [u8; 64] = [0; 64];
+/// }
+///
+/// resources! {
+///     pub struct Buffers {
+///         pub rx_buf: RX_BUF,
+///         pub tx_buf: TX_BUF,
+///     }
+/// }
+///
+/// let tx_buf = TX_BUF::take().unwrap();
+/// assert!(Buffers::take().is_none());
+///
+/// // `RX_BUF` can still be taken
+/// assert!(RX_BUF::take().is_some());
+/// # drop(tx_buf);
+/// # }
+/// # #[cfg(not(feature = "take"))]
+/// # fn main() {}
+/// ```
+#[macro_export]
+macro_rules! resources {
+    ($(#[$attr:meta])* $vis:vis struct $name:ident {
+        $($(#[$fattr:meta])* $fvis:vis $field:ident: $proxy:ty),* $(,)*
+    }) => {
+        $(#[$attr])*
+        $vis struct $name {
+            $($(#[$fattr])* $fvis $field: $proxy,)*
+        }
+
+        impl $name {
+            /// Creates all the singletons
+            ///
+            /// # Safety
+            ///
+            /// It's UB to create more than one instance of any of these singletons
+            #[inline]
+            $vis unsafe fn new() -> Self {
+                $name {
+                    $($field: <$proxy as $crate::Singleton>::new(),)*
+                }
+            }
+        }
+
+        $crate::__resources_take!($vis $name { $($field: $proxy),* });
+    };
+}
+
+/// The `take` constructor of `resources!`
+#[cfg(feature = "take")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __resources_take {
+    ($vis:vis $name:ident { $($field:ident: $proxy:ty),* }) => {
+        impl $name {
+            /// Takes all the singletons
+            ///
+            /// Returns `None` if any of the singletons has already been taken
+            #[inline]
+            $vis fn take() -> Option<Self> {
+                $(let $field = <$proxy>::take();)*
+
+                if true $(&& $field.is_some())* {
+                    Some($name {
+                        $($field: $field.unwrap(),)*
+                    })
+                } else {
+                    // give back the singletons that were taken so they can be taken again
+                    $(
+                        if let Some(proxy) = $field {
+                            <$proxy>::__give_back(proxy);
+                        }
+                    )*
+
+                    None
+                }
+            }
+        }
+    };
+}
+
+/// The `take` constructor of `resources!`; there's none without the `take` feature
+#[cfg(not(feature = "take"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __resources_take {
+    ($($tt:tt)*) => {};
+}
+
+/// Declares a singleton in expression position and initializes it
+///
+/// This is the owned proxy counterpart of `cortex_m::singleton!`. `singleton!(: T = expr)`
+/// declares an [`init`](attr.Singleton.html) variable of type `T` and evaluates to
+/// `Some(proxy)` the first time it runs, with the variable initialized to `expr`, and to `None`
+/// every time after that. The variable can be named, e.g. for debugging, with
+/// `singleton!(NAME: T = expr)`.
+///
+/// The proxy type is declared inside the expansion of the macro so it can't be named; use `impl
+/// SingletonMut<Type = T>`, or a generic parameter, to pass it around.
+///
+/// ```
+/// use owned_singleton::{singleton, SingletonMut};
+///
+/// fn init() -> Option<impl SingletonMut<Type = [u8; 1024]>> {
+///     singleton!(: [u8; 1024] = [0; 1024])
+/// }
+///
+/// let mut buf = init().unwrap();
+/// buf[0] = 1;
+///
+/// // the second time around the variable has already been initialized
+/// assert!(init().is_none());
+///
+/// // the initializer can use runtime data
+/// let len = buf.len();
+/// let len = singleton!(LEN: usize = len).unwrap();
+/// assert_eq!(*len, 1024);
+/// ```
+#[cfg(feature = "macros")]
+#[macro_export]
+macro_rules! singleton {
+    ($name:ident: $ty:ty = $expr:expr) => {{
+        #[$crate::Singleton(init)]
+        static mut $name: $ty;
+
+        $name::init($expr).ok()
+    }};
+    (: $ty:ty = $expr:expr) => {
+        $crate::singleton!(VAR: $ty = $expr)
+    };
+}
+
+/// Declares a singleton without the `Singleton` proc macro attribute
+///
+/// This covers the common case: a `static mut` variable with a constant initializer. The variable
+/// gets a proxy that implements `Singleton`, `SingletonMut`, `Deref` and `DerefMut` and, with the
+/// `take` feature, a checked `take` constructor. Put `#[singleton(Send, Sync)]` first to opt into
+/// the `Send` and `Sync` implementations, as the `Send` and `Sync` arguments of the attribute do.
+///
+/// Unlike the attribute, this macro doesn't need `syn`, `quote` or `proc-macro2`. Disable the
+/// default `macros` feature to remove the proc macro crate, and its dependencies, from the build.
+///
+/// ```
+/// use owned_singleton::{declare_singleton, Singleton};
+///
+/// declare_singleton! {
+///     /// The number of received frames
+///     pub static mut FRAMES: u32 = 0;
+/// }
+///
+/// declare_singleton! {
+///     #[singleton(Send, Sync)]
+///     static mut RX_BUF: [u8; 64] = [0; 64];
+/// }
+///
+/// let mut frames = unsafe { FRAMES::new() };
+/// *frames += 1;
+///
+/// let mut rx_buf = unsafe { RX_BUF::new() };
+/// std::thread::spawn(move || rx_buf[0] = 1).join().unwrap();
+///
+/// assert_eq!(*frames, 1);
+/// assert_eq!(RX_BUF::NAME, "RX_BUF");
+/// ```
+#[macro_export]
+macro_rules! declare_singleton {
+    (
+        #[singleton($($marker:ident),* $(,)*)]
+        $(#[$attr:meta])*
+        $vis:vis static mut $name:ident: $ty:ty = $expr:expr;
+    ) => {
+        $crate::declare_singleton! {
+            $(#[$attr])*
+            $vis static mut $name: $ty = $expr;
+        }
+
+        $($crate::__declare_singleton_marker!($marker $name $ty);)*
+    };
+    (
+        $(#[$attr:meta])*
+        $vis:vis static mut $name:ident: $ty:ty = $expr:expr;
+    ) => {
+        $(#[$attr])*
+        #[allow(non_camel_case_types)]
+        $vis struct $name {
+            _marker: $crate::export::NotSendOrSync,
+        }
+
+        $crate::impl_singleton! {
+            unsafe impl SingletonMut for $name {
+                type Type = $ty;
+                new = $name {
+                    _marker: $crate::export::PhantomData,
+                };
+                get = {
+                    // NOTE declared here so it can only be reached through the proxy
+                    static VAR: $crate::export::Cell<$ty> = $crate::export::Cell::new($expr);
+
+                    VAR.get()
+                };
+            }
+        }
+
+        $crate::__declare_singleton_take!($vis $name);
+    };
+}
+
+/// The `Send` and `Sync` opt-ins of `declare_singleton!`
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_singleton_marker {
+    (Send $name:ident $ty:ty) => {
+        unsafe impl Send for $name where $ty: Send {}
+    };
+    (Sync $name:ident $ty:ty) => {
+        unsafe impl Sync for $name where $ty: Sync {}
+    };
+    ($marker:ident $name:ident $ty:ty) => {
+        compile_error!(concat!(
+            "expected `Send` or `Sync`, found `",
+            stringify!($marker),
+            "`"
+        ));
+    };
+}
+
+/// The `take` constructor of `declare_singleton!`
+#[cfg(feature = "take")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_singleton_take {
+    ($vis:vis $name:ident) => {
+        impl $name {
+            /// Takes the singleton
+            ///
+            /// Returns `None` if the singleton has already been taken
+            #[inline]
+            $vis fn take() -> Option<Self> {
+                if Self::__taken().acquire() {
+                    Some($name {
+                        _marker: $crate::export::PhantomData,
+                    })
+                } else {
+                    None
+                }
+            }
+
+            // used by `resources!` to undo a partial take
+            #[doc(hidden)]
+            #[allow(clippy::forget_non_drop)]
+            #[inline]
+            $vis fn __give_back(self) {
+                $crate::export::mem::forget(self);
+                Self::__taken().release();
+            }
+
+            #[inline]
+            fn __taken() -> &'static $crate::export::Flag {
+                static TAKEN: $crate::export::Flag = $crate::export::Flag::new();
+
+                &TAKEN
+            }
+        }
+    };
+}
+
+/// The `take` constructor of `declare_singleton!`; there's none without the `take` feature
+#[cfg(not(feature = "take"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __declare_singleton_take {
+    ($($tt:tt)*) => {};
+}
+
+/// Implements `Singleton` on a hand-written proxy over existing storage
+///
+/// For storage the `Singleton` attribute can't declare, e.g. variables emitted by a code generator
+/// or symbols with unusual linkage. `new` is the expression that creates the proxy and `get` the
+/// expression that returns the address of the variable, as a `*mut Type`.
+/// `unsafe impl Singleton` implements `Singleton`, `Deref` and `StableDeref`; `unsafe impl
+/// SingletonMut` implements `SingletonMut` and `DerefMut` on top of that.
+///
+/// # Safety
+///
+/// The `unsafe impl` is a promise that the proxy upholds the contract of `Singleton`:
+///
+/// - `get` always returns the same non-null, aligned pointer to an initialized value of type
+///   `Type`.
+///
+/// - The value is only accessed through the proxy. In particular, the proxy can only be created
+///   through `Singleton::new`, so its fields must be private.
+///
+/// - For `SingletonMut`, the proxy is not `Copy` or `Clone`.
+///
+/// ```
+/// # #![allow(unused_unsafe)]
+/// use owned_singleton::{impl_singleton, Singleton};
+///
+/// mod generated {
+///     pub static mut MAILBOX: [u32; 8] = [0; 8];
+/// }
+///
+/// pub struct Mailbox {
+///     _private: (),
+/// }
+///
+/// impl_singleton! {
+///     unsafe impl SingletonMut for Mailbox {
+///         type Type = [u32; 8];
+///         new = Mailbox { _private: () };
+///         get = unsafe { std::ptr::addr_of_mut!(generated::MAILBOX) };
+///     }
+/// }
+///
+/// let mut mailbox = unsafe { Mailbox::new() };
+/// mailbox[0] = 1;
+///
+/// assert_eq!(Mailbox::NAME, "Mailbox");
+/// assert_eq!(unsafe { Mailbox::steal()[0] }, 1);
+/// ```
+#[macro_export]
+macro_rules! impl_singleton {
+    (unsafe impl Singleton for $proxy:ident {
+        type Type = $ty:ty;
+        new = $new:expr;
+        get = $get:expr;
+    }) => {
+        unsafe impl $crate::Singleton for $proxy {
+            type Type = $ty;
+
+            const NAME: &'static str = stringify!($proxy);
+
+            #[inline]
+            unsafe fn new() -> Self {
+                $new
+            }
+
+            #[inline]
+            fn get() -> *mut Self::Type {
+                $get
+            }
+        }
+
+        impl $crate::export::Deref for $proxy {
+            type Target = $ty;
+
+            #[inline]
+            fn deref(&self) -> &$ty {
+                unsafe { &*<$proxy as $crate::Singleton>::get() }
+            }
+        }
+
+        unsafe impl $crate::export::StableDeref for $proxy {}
+    };
+    (unsafe impl SingletonMut for $proxy:ident {
+        type Type = $ty:ty;
+        new = $new:expr;
+        get = $get:expr;
+    }) => {
+        $crate::impl_singleton! {
+            unsafe impl Singleton for $proxy {
+                type Type = $ty;
+                new = $new;
+                get = $get;
+            }
+        }
+
+        unsafe impl $crate::SingletonMut for $proxy {}
+
+        impl $crate::export::DerefMut for $proxy {
+            #[inline]
+            fn deref_mut(&mut self) -> &mut $ty {
+                unsafe { &mut *<$proxy as $crate::Singleton>::get() }
+            }
+        }
+    };
+}
+
+/// Sets the [`ceiling::Impl`](ceiling/trait.Impl.html) that masks interrupts for
+/// [`Ceiling`](ceiling/struct.Ceiling.html)
+///
+/// Call it once, in the application; see the [`ceiling`](ceiling/index.html) module for an example.
+#[macro_export]
+macro_rules! set_ceiling_impl {
+    ($t:ty) => {
+        #[no_mangle]
+        unsafe fn _owned_singleton_ceiling_raise(priority: u8) -> u8 {
+            <$t as $crate::ceiling::Impl>::raise(priority)
+        }
+
+        #[no_mangle]
+        unsafe fn _owned_singleton_ceiling_restore(state: u8) {
+            <$t as $crate::ceiling::Impl>::restore(state)
+        }
+    };
+}
@@ -0,0 +1,145 @@
+//! Exclusive ownership of memory-mapped regions
+//!
+//! [`Owned<T, ADDR>`](struct.Owned.html) is the proxy of the `T` that lives at the fixed address
+//! `ADDR`, e.g. the register block of a peripheral or a shared-memory block defined by a vendor.
+//! Unlike the `address` argument of the `Singleton` attribute no `static` variable is declared:
+//! the address is part of the type, so drivers can be generic over it.
+//!
+//! The region is accessed with volatile operations through `read`, `write` and `modify`. The
+//! `Deref` implementations are meant for register block types whose fields already perform
+//! volatile accesses.
+//!
+//! ```no_run
+//! use owned_singleton::mmio::Owned;
+//!
+//! // the `SYST_CVR` register of ARM Cortex-M
+//! type CurrentValue = Owned<u32, 0xE000_E018>;
+//!
+//! let mut cvr = unsafe { <CurrentValue as owned_singleton::Singleton>::new() };
+//! cvr.write(0);
+//! let _ticks = cvr.read();
+//! ```
+//!
+//! The address is checked at compile time:
+//!
+//! ```compile_fail
+//! use owned_singleton::mmio::Owned;
+//! use owned_singleton::Singleton;
+//!
+//! // error: `ADDR` must be a non-null address aligned to `T`
+//! let _ = unsafe { Owned::<u32, 0x2000_0002>::new() };
+//! ```
+
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+use stable_deref_trait::StableDeref;
+
+use {Singleton, SingletonMut};
+
+/// The proxy of the `T` located at address `ADDR`
+pub struct Owned<T, const ADDR: usize> {
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T, const ADDR: usize> Owned<T, ADDR> {
+    /// Rejects addresses that can't hold a `T`
+    const CHECK: () = assert!(
+        ADDR != 0 && ADDR % mem::align_of::<T>() == 0,
+        "`ADDR` must be a non-null address aligned to `T`"
+    );
+
+    /// Returns the address of the region
+    #[inline]
+    pub const fn ptr() -> *mut T {
+        ADDR as *mut T
+    }
+
+    /// Reads the region using a volatile load
+    #[inline]
+    pub fn read(&self) -> T
+    where
+        T: Copy,
+    {
+        unsafe { ptr::read_volatile(Self::ptr()) }
+    }
+
+    /// Writes `value` into the region using a volatile store
+    #[inline]
+    pub fn write(&mut self, value: T)
+    where
+        T: Copy,
+    {
+        unsafe { ptr::write_volatile(Self::ptr(), value) }
+    }
+
+    /// Reads the region, passes it to `f` and writes the result back using volatile operations
+    #[inline]
+    pub fn modify<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut T),
+        T: Copy,
+    {
+        let mut value = self.read();
+        f(&mut value);
+        self.write(value);
+    }
+}
+
+unsafe impl<T, const ADDR: usize> Singleton for Owned<T, ADDR> {
+    type Type = T;
+
+    const NAME: &'static str = "Owned";
+
+    /// # Safety
+    ///
+    /// In addition to the requirements of `Singleton::new`, the region must hold a valid `T` and
+    /// must not overlap with memory reached by other means, e.g. an `Owned` proxy of another
+    /// type
+    #[inline]
+    unsafe fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = Self::CHECK;
+
+        Owned {
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn get() -> *mut T {
+        Self::ptr()
+    }
+}
+
+unsafe impl<T, const ADDR: usize> SingletonMut for Owned<T, ADDR> {}
+
+impl<T, const ADDR: usize> Deref for Owned<T, ADDR> {
+    type Target = T;
+
+    #[inline]
+    fn deref(&self) -> &T {
+        unsafe { &*Self::ptr() }
+    }
+}
+
+impl<T, const ADDR: usize> DerefMut for Owned<T, ADDR> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *Self::ptr() }
+    }
+}
+
+unsafe impl<T, const ADDR: usize> StableDeref for Owned<T, ADDR> {}
+
+// NOTE like the variable behind it, the proxy can be moved to other contexts if `T` can
+unsafe impl<T, const ADDR: usize> Send for Owned<T, ADDR> where T: Send {}
+
+impl<T, const ADDR: usize> fmt::Debug for Owned<T, ADDR> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Owned({:#x})", ADDR)
+    }
+}
@@ -0,0 +1,143 @@
+//! Write-once singletons
+//!
+//! [`OnceSingleton`](struct.OnceSingleton.html) turns the proxy of an `Option<T>` variable into a
+//! cell that can be written once, at runtime, and then shared read-only.
+
+use core::fmt;
+
+use atomic::{AtomicU8, Ordering};
+use SingletonMut;
+
+/// The variable is `None`
+const EMPTY: u8 = 0;
+/// The variable is being written
+const BUSY: u8 = 1;
+/// The variable is `Some`; it won't change anymore
+const READY: u8 = 2;
+
+/// A cell, backed by the `Option<T>` variable behind the singleton `S`, that can be written once
+pub struct OnceSingleton<S> {
+    singleton: S,
+    state: AtomicU8,
+}
+
+impl<S, T> OnceSingleton<S>
+where
+    S: SingletonMut<Type = Option<T>>,
+{
+    /// Wraps the proxy
+    ///
+    /// If the variable is already `Some` the cell starts out initialized.
+    pub fn new(singleton: S) -> Self {
+        let state = if singleton.is_some() { READY } else { EMPTY };
+
+        OnceSingleton {
+            singleton,
+            state: AtomicU8::new(state),
+        }
+    }
+
+    /// Returns the value, if the cell has been initialized
+    #[inline]
+    pub fn get(&self) -> Option<&T> {
+        if self.state.load(Ordering::Acquire) == READY {
+            // NOTE the value won't change anymore
+            unsafe { (*S::get()).as_ref() }
+        } else {
+            None
+        }
+    }
+
+    /// Initializes the cell with `value`
+    ///
+    /// Returns `value` back if the cell has already been initialized, or is being initialized
+    pub fn set(&self, value: T) -> Result<(), T> {
+        if !self.begin() {
+            return Err(value);
+        }
+
+        unsafe { *S::get() = Some(value) }
+        self.state.store(READY, Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Returns the value, initializing the cell with `f` if it's empty
+    ///
+    /// # Panics
+    ///
+    /// This method panics if it's called while the cell is being initialized, e.g. from `f` or
+    /// from an interrupt handler that preempted the initialization
+    pub fn get_or_init<F>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> T,
+    {
+        if let Some(value) = self.get() {
+            return value;
+        }
+
+        if self.begin() {
+            unsafe { *S::get() = Some(f()) }
+            self.state.store(READY, Ordering::Release);
+        }
+
+        self.get()
+            .expect("`get_or_init` was called during the initialization of the cell")
+    }
+
+    /// Releases the proxy
+    pub fn into_inner(self) -> S {
+        self.singleton
+    }
+
+    /// Moves the cell from the `EMPTY` state to the `BUSY` state
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    fn begin(&self) -> bool {
+        self.state
+            .compare_exchange(EMPTY, BUSY, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Moves the cell from the `EMPTY` state to the `BUSY` state
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    fn begin(&self) -> bool {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            if self.state.load(Ordering::Relaxed) == EMPTY {
+                self.state.store(BUSY, Ordering::Relaxed);
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+impl<S, T> fmt::Debug for OnceSingleton<S>
+where
+    S: SingletonMut<Type = Option<T>>,
+    T: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.get() {
+            Some(value) => f.debug_tuple("OnceSingleton").field(value).finish(),
+            None => f.write_str("OnceSingleton(<uninit>)"),
+        }
+    }
+}
+
+// the value is written from one context and read from all of them
+unsafe impl<S, T> Sync for OnceSingleton<S>
+where
+    S: SingletonMut<Type = Option<T>> + Send,
+    T: Send + Sync,
+{
+}
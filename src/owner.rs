@@ -0,0 +1,83 @@
+//! Records where singletons were taken
+
+use core::fmt;
+use core::panic::Location;
+use core::ptr;
+
+use atomic::{AtomicPtr, Ordering};
+
+/// The location of the `take` call that took a singleton
+pub struct Owner {
+    location: AtomicPtr<Location<'static>>,
+}
+
+impl Owner {
+    /// Creates a slot that holds no location
+    pub const fn new() -> Self {
+        Owner {
+            location: AtomicPtr::new(ptr::null_mut()),
+        }
+    }
+
+    /// Returns the recorded location, if any
+    #[inline]
+    pub fn get(&self) -> Option<&'static Location<'static>> {
+        // NOTE only `set` writes non-null pointers and those come from `&'static` references
+        unsafe { self.location.load(Ordering::Relaxed).as_ref() }
+    }
+
+    /// Records `location`
+    #[inline]
+    pub fn set(&self, location: &'static Location<'static>) {
+        self.location
+            .store(location as *const _ as *mut _, Ordering::Relaxed)
+    }
+
+    /// Forgets the recorded location
+    #[inline]
+    pub fn clear(&self) {
+        self.location.store(ptr::null_mut(), Ordering::Relaxed)
+    }
+}
+
+impl Default for Owner {
+    fn default() -> Self {
+        Owner::new()
+    }
+}
+
+/// The error returned by `try_take` when the singleton has already been taken
+#[derive(Clone, Copy, Debug)]
+pub struct TakeError {
+    name: &'static str,
+    taken_at: Option<&'static Location<'static>>,
+}
+
+impl TakeError {
+    #[doc(hidden)]
+    pub fn new(name: &'static str, taken_at: Option<&'static Location<'static>>) -> Self {
+        TakeError { name, taken_at }
+    }
+
+    /// Returns the name of the singleton
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the location of the `take` call that took the singleton
+    ///
+    /// This is `None` if the location hasn't been recorded yet, e.g. because the `take` call was
+    /// preempted right after taking the singleton.
+    pub fn taken_at(&self) -> Option<&'static Location<'static>> {
+        self.taken_at
+    }
+}
+
+impl fmt::Display for TakeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.taken_at {
+            Some(location) => write!(f, "`{}` has already been taken at {}", self.name, location),
+            None => write!(f, "`{}` has already been taken", self.name),
+        }
+    }
+}
@@ -0,0 +1,229 @@
+//! Panic messages that survive resets
+//!
+//! [`report`](fn.report.html) is meant to be called from the `#[panic_handler]`; it writes the
+//! panic message into a byte buffer that's placed in the `.uninit` linker section, like the
+//! variables of `noinit` singletons, so it's not cleared at startup. After the reset
+//! [`get_panic_message`](fn.get_panic_message.html) hands out the message as a singleton.
+//!
+//! ```ignore
+//! #[panic_handler]
+//! fn panic(info: &PanicInfo) -> ! {
+//!     owned_singleton::panic_persist::report(info);
+//!
+//!     cortex_m::peripheral::SCB::sys_reset()
+//! }
+//!
+//! #[entry]
+//! fn main() -> ! {
+//!     if let Some(message) = owned_singleton::panic_persist::get_panic_message() {
+//!         log::error!("panicked before the reset: {}", message);
+//!         message.clear();
+//!     }
+//!
+//!     // ..
+//! }
+//! ```
+
+use core::cell::UnsafeCell;
+use core::mem::MaybeUninit;
+use core::ops::Deref;
+use core::panic::PanicInfo;
+use core::{fmt, ptr, slice, str};
+
+use atomic::{AtomicU8, Ordering};
+use export::Header;
+
+/// The size of the buffer, in bytes; longer messages are truncated
+pub const CAPACITY: usize = 1024;
+
+#[repr(C)]
+struct Message {
+    len: usize,
+    bytes: [u8; CAPACITY],
+}
+
+struct Persisted {
+    header: Header,
+    message: UnsafeCell<MaybeUninit<Message>>,
+}
+
+unsafe impl Sync for Persisted {}
+
+// NOTE Mach-O section names have a different format; Apple targets don't have a reset to survive
+#[cfg_attr(
+    not(target_vendor = "apple"),
+    link_section = ".uninit.owned_singleton.panic_persist"
+)]
+static PERSISTED: Persisted = Persisted {
+    header: Header::uninit(),
+    message: UnsafeCell::new(MaybeUninit::uninit()),
+};
+
+/// The message has not been handed out
+const FREE: u8 = 0;
+/// The message has been handed out by `get_panic_message`
+const TAKEN: u8 = 1;
+
+static STATE: AtomicU8 = AtomicU8::new(FREE);
+
+/// Writes the panic message into the buffer, replacing the previous one
+pub fn report(info: &PanicInfo) {
+    report_fmt(format_args!("{}", info))
+}
+
+/// Writes `args` into the buffer, replacing the previous message
+///
+/// This is `report` for panic hooks, and other code, that doesn't have a `core::panic::PanicInfo`
+pub fn report_fmt(args: fmt::Arguments) {
+    let message = PERSISTED.message.get() as *mut Message;
+
+    // NOTE invalidate first so a reset in the middle of the write doesn't leave a corrupted
+    // message behind
+    PERSISTED.header.invalidate();
+
+    let mut cursor = Cursor { message, len: 0 };
+    // NOTE the message is truncated if it doesn't fit
+    let _ = fmt::write(&mut cursor, args);
+
+    unsafe {
+        // zero the rest of the buffer so the checksum covers only initialized memory
+        let bytes = ptr::addr_of_mut!((*message).bytes) as *mut u8;
+        for i in cursor.len..CAPACITY {
+            ptr::write_volatile(bytes.add(i), 0);
+        }
+
+        ptr::write_volatile(ptr::addr_of_mut!((*message).len), cursor.len);
+        PERSISTED.header.seal(message, true);
+    }
+}
+
+/// Returns the message written by `report` before the last reset, if any
+///
+/// This returns `Some` at most once.
+pub fn get_panic_message() -> Option<PanicMessage> {
+    let message = PERSISTED.message.get() as *const Message;
+
+    unsafe {
+        if !PERSISTED.header.is_valid(message, true) {
+            return None;
+        }
+
+        let len = ptr::read_volatile(ptr::addr_of!((*message).len));
+        if len > CAPACITY {
+            return None;
+        }
+
+        let bytes = slice::from_raw_parts(ptr::addr_of!((*message).bytes) as *const u8, len);
+        if str::from_utf8(bytes).is_err() || !take() {
+            return None;
+        }
+    }
+
+    Some(PanicMessage { _private: () })
+}
+
+#[cfg(all(
+    not(feature = "cs-take"),
+    any(target_has_atomic = "8", feature = "portable-atomic")
+))]
+#[inline]
+fn take() -> bool {
+    STATE
+        .compare_exchange(FREE, TAKEN, Ordering::Acquire, Ordering::Relaxed)
+        .is_ok()
+}
+
+#[cfg(any(
+    feature = "cs-take",
+    all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+))]
+#[inline]
+fn take() -> bool {
+    // NOTE only atomic loads and stores are used so this works on targets without CAS
+    critical_section::with(|_| {
+        if STATE.load(Ordering::Relaxed) == FREE {
+            STATE.store(TAKEN, Ordering::Relaxed);
+            true
+        } else {
+            false
+        }
+    })
+}
+
+/// The panic message written before the last reset
+///
+/// There's at most one instance of this singleton; it dereferences to the text of the message.
+pub struct PanicMessage {
+    _private: (),
+}
+
+impl PanicMessage {
+    /// Returns the text of the message
+    pub fn as_str(&self) -> &str {
+        let message = PERSISTED.message.get() as *const Message;
+
+        // NOTE `get_panic_message` already checked the length and the encoding
+        unsafe {
+            let len = (*message).len;
+            str::from_utf8_unchecked(slice::from_raw_parts(
+                ptr::addr_of!((*message).bytes) as *const u8,
+                len,
+            ))
+        }
+    }
+
+    /// Discards the message so it's not reported again after the next reset
+    pub fn clear(self) {
+        PERSISTED.header.invalidate()
+    }
+}
+
+impl Deref for PanicMessage {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for PanicMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for PanicMessage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+/// Appends text to the persisted message
+struct Cursor {
+    message: *mut Message,
+    len: usize,
+}
+
+impl fmt::Write for Cursor {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let buffer = unsafe { ptr::addr_of_mut!((*self.message).bytes) as *mut u8 };
+
+        // truncate at a character boundary
+        let mut n = s.len().min(CAPACITY - self.len);
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+
+        // NOTE volatile writes because the buffer outlives the program
+        for (i, byte) in s.as_bytes()[..n].iter().enumerate() {
+            unsafe { ptr::write_volatile(buffer.add(self.len + i), *byte) }
+        }
+        self.len += n;
+
+        if n == s.len() {
+            Ok(())
+        } else {
+            Err(fmt::Error)
+        }
+    }
+}
@@ -0,0 +1,393 @@
+//! Typed memory pool
+//!
+//! The [`Storage`](struct.Storage.html) of a pool lives in a `static mut` variable.
+//! [`Pool::new`](struct.Pool.html#method.new) consumes the proxy of that variable and returns an
+//! allocator that hands out [`Box`](struct.Box.html)es. A `Box` is a one byte index into the
+//! storage; dropping it drops the value and returns its slot to the pool.
+//!
+//! With the `allocator-api` feature, which requires a nightly compiler, `&Pool` implements
+//! `core::alloc::Allocator`. Each allocation takes one slot so it must fit in a `T`; zero sized
+//! `T`s are not supported.
+
+use core::{
+    cell::UnsafeCell,
+    cmp, fmt, hash,
+    marker::PhantomData,
+    mem::{self, MaybeUninit},
+    ops::{Deref, DerefMut},
+    ptr,
+};
+
+#[cfg(feature = "allocator-api")]
+use core::alloc::{AllocError, Allocator, Layout};
+#[cfg(feature = "allocator-api")]
+use core::ptr::NonNull;
+
+use atomic::{AtomicU8, Ordering};
+use {export, SingletonMut};
+
+/// The value of a free slot in `Storage.used`
+const FREE: u8 = 0;
+
+/// The value of an allocated slot in `Storage.used`
+const USED: u8 = 1;
+
+/// The storage of a pool of `N` values of type `T`
+///
+/// `N` can't exceed 256, the number of slots a `Box` can index
+pub struct Storage<T, const N: usize> {
+    slots: UnsafeCell<MaybeUninit<[T; N]>>,
+    used: [AtomicU8; N],
+}
+
+impl<T, const N: usize> Storage<T, N> {
+    /// Creates storage whose slots are all free
+    pub const fn new() -> Self {
+        // NOTE a `const` item can be repeated even though `AtomicU8` isn't `Copy`
+        #[allow(clippy::declare_interior_mutable_const)]
+        const SLOT: AtomicU8 = AtomicU8::new(FREE);
+
+        Storage {
+            slots: UnsafeCell::new(MaybeUninit::uninit()),
+            used: [SLOT; N],
+        }
+    }
+
+    /// Claims a free slot; returns its index
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    fn claim(&self) -> Option<u8> {
+        self.used
+            .iter()
+            .position(|used| {
+                used.compare_exchange(FREE, USED, Ordering::Acquire, Ordering::Relaxed)
+                    .is_ok()
+            })
+            .map(|index| index as u8)
+    }
+
+    /// Claims a free slot; returns its index
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    fn claim(&self) -> Option<u8> {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            self.used.iter().position(|used| {
+                if used.load(Ordering::Relaxed) == FREE {
+                    used.store(USED, Ordering::Relaxed);
+                    true
+                } else {
+                    false
+                }
+            })
+        })
+        .map(|index| index as u8)
+    }
+}
+
+/// Pool storage that boxes can reach through the singleton alone
+///
+/// This trait is implemented by `Storage` and can't be implemented outside this crate.
+pub trait Slots: private::Sealed {
+    /// The type of the values in the pool
+    type Item;
+
+    #[doc(hidden)]
+    fn slot(&self, index: u8) -> *mut Self::Item;
+
+    #[doc(hidden)]
+    fn release(&self, index: u8);
+}
+
+impl<T, const N: usize> Slots for Storage<T, N> {
+    type Item = T;
+
+    fn slot(&self, index: u8) -> *mut T {
+        unsafe { (self.slots.get() as *mut T).add(usize::from(index)) }
+    }
+
+    fn release(&self, index: u8) {
+        self.used[usize::from(index)].store(FREE, Ordering::Release);
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl<T, const N: usize> Sealed for super::Storage<T, N> {}
+}
+
+impl<T, const N: usize> Default for Storage<T, N> {
+    fn default() -> Self {
+        Storage::new()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Storage<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let used = self
+            .used
+            .iter()
+            .filter(|used| used.load(Ordering::Relaxed) == USED)
+            .count();
+
+        f.debug_struct("Storage")
+            .field("used", &used)
+            .field("capacity", &N)
+            .finish()
+    }
+}
+
+/// A pool of values of type `T` stored in the variable behind the singleton `S`
+pub struct Pool<S, T> {
+    _marker: PhantomData<(S, T)>,
+}
+
+impl<S, T, const N: usize> Pool<S, T>
+where
+    S: SingletonMut<Type = Storage<T, N>>,
+{
+    /// Turns the proxy into a pool
+    ///
+    /// # Panics
+    ///
+    /// This function panics if the storage has more than 256 slots
+    pub fn new(singleton: S) -> Self {
+        assert!(N <= 256, "a `Pool` can't have more than 256 slots");
+
+        export::adopt(singleton);
+
+        Pool {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Moves `value` into a free slot of the pool; returns it back if the pool is exhausted
+    pub fn alloc(&self, value: T) -> Result<Box<S>, T> {
+        let storage = storage::<S>();
+
+        match storage.claim() {
+            Some(index) => {
+                unsafe { ptr::write(storage.slot(index), value) }
+
+                Ok(Box {
+                    index,
+                    _singleton: PhantomData,
+                })
+            }
+            None => Err(value),
+        }
+    }
+
+    /// Returns the number of slots in the pool
+    pub fn capacity(&self) -> usize {
+        N
+    }
+}
+
+#[cfg(feature = "allocator-api")]
+unsafe impl<S, T, const N: usize> Allocator for &Pool<S, T>
+where
+    S: SingletonMut<Type = Storage<T, N>>,
+{
+    fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
+        let size = mem::size_of::<T>();
+        if size == 0 || layout.size() > size || layout.align() > mem::align_of::<T>() {
+            return Err(AllocError);
+        }
+
+        let storage = storage::<S>();
+        let index = storage.claim().ok_or(AllocError)?;
+        let ptr = storage.slot(index) as *mut u8;
+
+        Ok(NonNull::slice_from_raw_parts(
+            unsafe { NonNull::new_unchecked(ptr) },
+            size,
+        ))
+    }
+
+    unsafe fn deallocate(&self, ptr: NonNull<u8>, _: Layout) {
+        let storage = storage::<S>();
+        let offset = ptr.as_ptr() as usize - storage.slot(0) as usize;
+        storage.release((offset / mem::size_of::<T>()) as u8);
+    }
+}
+
+impl<S, T> fmt::Debug for Pool<S, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Pool")
+    }
+}
+
+// the slots are claimed and released atomically; boxes may be sent to other contexts
+unsafe impl<S, T> Sync for Pool<S, T> where T: Send {}
+
+/// A value allocated from the pool behind the singleton `S`
+pub struct Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+{
+    index: u8,
+    _singleton: PhantomData<S>,
+}
+
+impl<S> Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+{
+    /// Moves the value out of the box and returns its slot to the pool
+    pub fn into_inner(self) -> <S::Type as Slots>::Item {
+        let storage = storage::<S>();
+        let value = unsafe { ptr::read(storage.slot(self.index)) };
+        storage.release(self.index);
+        mem::forget(self);
+        value
+    }
+}
+
+impl<S> Deref for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+{
+    type Target = <S::Type as Slots>::Item;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        unsafe { &*storage::<S>().slot(self.index) }
+    }
+}
+
+impl<S> DerefMut for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // NOTE the box is the only owner of its slot
+        unsafe { &mut *storage::<S>().slot(self.index) }
+    }
+}
+
+impl<S> Drop for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+{
+    fn drop(&mut self) {
+        let storage = storage::<S>();
+        unsafe { ptr::drop_in_place(storage.slot(self.index)) }
+        storage.release(self.index);
+    }
+}
+
+impl<S> fmt::Debug for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+impl<S> fmt::Display for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&**self, f)
+    }
+}
+
+impl<S> PartialEq for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: PartialEq,
+{
+    fn eq(&self, other: &Self) -> bool {
+        **self == **other
+    }
+}
+
+impl<S> Eq for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: Eq,
+{
+}
+
+impl<S> PartialOrd for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: PartialOrd,
+{
+    fn partial_cmp(&self, other: &Self) -> Option<cmp::Ordering> {
+        (**self).partial_cmp(&**other)
+    }
+}
+
+impl<S> Ord for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: Ord,
+{
+    fn cmp(&self, other: &Self) -> cmp::Ordering {
+        (**self).cmp(&**other)
+    }
+}
+
+impl<S> hash::Hash for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: hash::Hash,
+{
+    fn hash<H>(&self, state: &mut H)
+    where
+        H: hash::Hasher,
+    {
+        (**self).hash(state)
+    }
+}
+
+unsafe impl<S> Send for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: Send,
+{
+}
+
+unsafe impl<S> Sync for Box<S>
+where
+    S: SingletonMut,
+    S::Type: Slots,
+    <S::Type as Slots>::Item: Sync,
+{
+}
+
+/// Returns a shared reference to the storage behind the singleton `S`
+///
+/// NOTE all the mutable state of `Storage` is behind `UnsafeCell` or atomics so shared references
+/// are all the pool and its boxes need
+fn storage<'a, S>() -> &'a S::Type
+where
+    S: SingletonMut,
+{
+    unsafe { &*S::get() }
+}
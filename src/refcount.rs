@@ -0,0 +1,279 @@
+//! Reference counted read-only handles
+
+use core::{fmt, marker::PhantomData, mem, ops::Deref};
+
+use atomic::checked::AtomicUsize;
+use atomic::Ordering;
+use Singleton;
+
+/// Singletons whose proxy can be shared through reference counted [`Ref`] handles
+///
+/// The `Singleton` attribute implements this trait when the `refcount` argument is used.
+///
+/// # Safety
+///
+/// `count` must always return the same counter and the counter must not be used for anything else
+pub unsafe trait RefCounted: Singleton {
+    #[doc(hidden)]
+    fn count() -> &'static RefCount;
+}
+
+/// Number of `Ref` handles to a singleton
+#[doc(hidden)]
+pub struct RefCount {
+    count: AtomicUsize,
+}
+
+impl RefCount {
+    /// Creates a counter with no handles
+    #[cfg(not(all(loom, test)))]
+    pub const fn new() -> Self {
+        RefCount {
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Creates a counter with no handles
+    // NOTE `loom`'s atomics can't be created in const context
+    #[cfg(all(loom, test))]
+    pub fn new() -> Self {
+        RefCount {
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// Adds one handle
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    pub fn increment(&self) {
+        self.count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Adds one handle
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    pub fn increment(&self) {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            let count = self.count.load(Ordering::Relaxed);
+            self.count.store(count + 1, Ordering::Relaxed);
+        })
+    }
+
+    /// Removes one handle; returns `true` if it was the last one
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    pub fn decrement(&self) -> bool {
+        self.count.fetch_sub(1, Ordering::AcqRel) == 1
+    }
+
+    /// Removes one handle; returns `true` if it was the last one
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    pub fn decrement(&self) -> bool {
+        critical_section::with(|_| {
+            let count = self.count.load(Ordering::Relaxed);
+            self.count.store(count - 1, Ordering::Relaxed);
+            count == 1
+        })
+    }
+
+    /// Returns the number of handles
+    #[inline]
+    pub fn get(&self) -> usize {
+        self.count.load(Ordering::Acquire)
+    }
+}
+
+impl Default for RefCount {
+    fn default() -> Self {
+        RefCount::new()
+    }
+}
+
+/// A reference counted, read-only handle to the variable behind the singleton `S`
+///
+/// Cloning the handle increases a counter stored next to the singleton. Once only one handle is
+/// left it can be turned back into the proxy with `Ref::upgrade`. If the last handle is dropped
+/// instead, the proxy is dropped as well.
+pub struct Ref<S>
+where
+    S: RefCounted,
+{
+    _singleton: PhantomData<fn() -> S>,
+    _not_send_or_sync: PhantomData<*const ()>,
+}
+
+impl<S> Ref<S>
+where
+    S: RefCounted,
+{
+    /// Turns the proxy into the first handle
+    pub fn new(singleton: S) -> Self {
+        // dereferencing makes sure the variable is initialized (e.g. `lazy` singletons)
+        let _ = &*singleton;
+        mem::forget(singleton);
+        S::count().increment();
+
+        Ref {
+            _singleton: PhantomData,
+            _not_send_or_sync: PhantomData,
+        }
+    }
+
+    /// Turns the handle back into the proxy if it's the only handle left
+    pub fn upgrade(this: Self) -> Result<S, Self> {
+        // NOTE no other handle exists so the count can't change under us
+        if Ref::count(&this) == 1 {
+            S::count().decrement();
+            mem::forget(this);
+            Ok(unsafe { S::new() })
+        } else {
+            Err(this)
+        }
+    }
+
+    /// Returns the number of handles
+    pub fn count(_this: &Self) -> usize {
+        S::count().get()
+    }
+}
+
+impl<S> Clone for Ref<S>
+where
+    S: RefCounted,
+{
+    fn clone(&self) -> Self {
+        S::count().increment();
+
+        Ref {
+            _singleton: PhantomData,
+            _not_send_or_sync: PhantomData,
+        }
+    }
+}
+
+impl<S> Deref for Ref<S>
+where
+    S: RefCounted,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*S::get() }
+    }
+}
+
+impl<S> Drop for Ref<S>
+where
+    S: RefCounted,
+{
+    fn drop(&mut self) {
+        if S::count().decrement() {
+            // runs the destructor of the proxy, if any
+            drop(unsafe { S::new() })
+        }
+    }
+}
+
+impl<S> fmt::Debug for Ref<S>
+where
+    S: RefCounted,
+    S::Type: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// handles in different threads share the variable and the last one drops the proxy
+unsafe impl<S> Send for Ref<S>
+where
+    S: RefCounted + Send,
+    S::Type: Sync,
+{
+}
+
+unsafe impl<S> Sync for Ref<S>
+where
+    S: RefCounted + Send,
+    S::Type: Sync,
+{
+}
+
+// Model checks of the reference counting logic
+//
+// Run with `RUSTFLAGS="--cfg loom" cargo test --lib --release`
+#[cfg(all(loom, test))]
+mod tests {
+    use loom::cell::UnsafeCell;
+    use loom::sync::Arc;
+    use loom::thread;
+
+    use super::RefCount;
+
+    /// Exactly one of the handles that are dropped concurrently is the last one
+    #[test]
+    fn last_handle() {
+        loom::model(|| {
+            let count = Arc::new(RefCount::new());
+            count.increment();
+            count.increment();
+
+            let other = {
+                let count = count.clone();
+                thread::spawn(move || count.decrement())
+            };
+
+            let here = count.decrement();
+            let there = other.join().unwrap();
+
+            assert!(here ^ there);
+            assert_eq!(count.get(), 0);
+        });
+    }
+
+    /// Reads made through the other handles happen before the last handle drops the proxy
+    #[test]
+    fn last_handle_synchronizes() {
+        loom::model(|| {
+            let count = Arc::new(RefCount::new());
+            let variable = Arc::new(UnsafeCell::new(0));
+            count.increment();
+            count.increment();
+
+            // reads the variable through a handle, then drops the handle; the last one to be
+            // dropped runs the destructor of the proxy, which writes to the variable
+            let drop_handle = |count: &RefCount, variable: &UnsafeCell<i32>| {
+                // NOTE loom reports a data race if the read is not ordered before the write
+                variable.with(|ptr| unsafe { *ptr });
+                if count.decrement() {
+                    variable.with_mut(|ptr| unsafe { *ptr = -1 });
+                }
+            };
+
+            let other = {
+                let count = count.clone();
+                let variable = variable.clone();
+                thread::spawn(move || drop_handle(&count, &variable))
+            };
+
+            drop_handle(&count, &variable);
+
+            other.join().unwrap();
+        });
+    }
+}
@@ -0,0 +1,47 @@
+//! Registry of singletons
+//!
+//! With the `registry` feature enabled every singleton places a `Descriptor` in the
+//! `owned_singleton_registry` linker section. `iter` walks that section, which relies on the
+//! `__start_` / `__stop_` symbols that ELF linkers define for it.
+
+use core::{mem, slice};
+
+/// Describes the storage of a singleton
+#[repr(C)]
+pub struct Descriptor {
+    /// Path to the singleton, e.g. `app::BUFFER`
+    pub name: &'static str,
+    /// Address of the storage
+    pub address: *const (),
+    /// Size of the storage in bytes
+    pub size: usize,
+    /// Alignment of the storage in bytes
+    pub align: usize,
+    /// Linker section the storage was placed in, if one was picked by the `Singleton` attribute
+    pub section: Option<&'static str>,
+}
+
+// NOTE the address is only used for reporting purposes
+unsafe impl Sync for Descriptor {}
+
+/// Returns an iterator over the descriptors of all the singletons in the program
+pub fn iter() -> slice::Iter<'static, Descriptor> {
+    extern "C" {
+        static __start_owned_singleton_registry: u8;
+        static __stop_owned_singleton_registry: u8;
+    }
+
+    unsafe {
+        let start = &__start_owned_singleton_registry as *const u8 as *const Descriptor;
+        let stop = &__stop_owned_singleton_registry as *const u8 as *const Descriptor;
+        let len = (stop as usize - start as usize) / mem::size_of::<Descriptor>();
+
+        slice::from_raw_parts(start, len).iter()
+    }
+}
+
+// makes sure the section exists, and thus its start and stop symbols, even if the program has no
+// singletons
+#[link_section = "owned_singleton_registry"]
+#[used]
+static EMPTY: [Descriptor; 0] = [];
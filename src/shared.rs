@@ -0,0 +1,79 @@
+//! Read-only handles
+
+use core::{fmt, marker::PhantomData, ops::Deref};
+
+use {NotPerCpu, Singleton};
+
+/// A read-only handle to the variable behind the singleton `S`
+///
+/// Created by `Singleton::freeze`. Unlike the proxy it was created from, this handle can be freely
+/// copied but it only hands out shared references and can't be turned back into a proxy.
+pub struct Shared<S> {
+    _singleton: PhantomData<fn() -> S>,
+    _not_send_or_sync: PhantomData<*const ()>,
+}
+
+impl<S> Shared<S>
+where
+    S: Singleton,
+{
+    /// # Safety
+    ///
+    /// The variable behind `S` must be initialized and must not be mutated for the rest of the
+    /// program
+    pub(crate) unsafe fn new() -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = NotPerCpu::<S>::CHECK;
+
+        Shared {
+            _singleton: PhantomData,
+            _not_send_or_sync: PhantomData,
+        }
+    }
+}
+
+impl<S> Clone for Shared<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Shared<S> {}
+
+impl<S> Deref for Shared<S>
+where
+    S: Singleton,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*S::get() }
+    }
+}
+
+impl<S> fmt::Debug for Shared<S>
+where
+    S: Singleton,
+    S::Type: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+// copies of the handle can be in several threads at the same time so both traits need `Sync`
+unsafe impl<S> Send for Shared<S>
+where
+    S: Singleton,
+    S::Type: Sync,
+{
+}
+
+unsafe impl<S> Sync for Shared<S>
+where
+    S: Singleton,
+    S::Type: Sync,
+{
+}
@@ -0,0 +1,224 @@
+//! Single producer single consumer queue
+//!
+//! The [`Queue`](struct.Queue.html) lives in a `static mut` variable. [`split`](fn.split.html)
+//! consumes the proxy of that variable and returns zero sized [`Producer`](struct.Producer.html)
+//! and [`Consumer`](struct.Consumer.html) handles, which can be moved to different execution
+//! contexts, e.g. an interrupt handler and thread mode. Only atomic loads and stores are used so
+//! the queue also works on targets without compare-and-swap instructions.
+
+use core::{cell::UnsafeCell, fmt, marker::PhantomData, mem::MaybeUninit, ptr};
+
+use atomic::{AtomicUsize, Ordering};
+use {export, SingletonMut};
+
+/// The storage of a queue that can hold up to `N` elements of type `T`
+///
+/// `head` and `tail` run from `0` to `2 * N`, so a full queue can be told apart from an empty one
+/// without leaving a slot unused
+pub struct Queue<T, const N: usize> {
+    buffer: UnsafeCell<MaybeUninit<[T; N]>>,
+    // NOTE written only by the consumer
+    head: AtomicUsize,
+    // NOTE written only by the producer
+    tail: AtomicUsize,
+}
+
+impl<T, const N: usize> Queue<T, N> {
+    /// Creates an empty queue
+    pub const fn new() -> Self {
+        Queue {
+            buffer: UnsafeCell::new(MaybeUninit::uninit()),
+            head: AtomicUsize::new(0),
+            tail: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the maximum number of elements the queue can hold
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    /// Returns the number of elements in the queue
+    pub fn len(&self) -> usize {
+        let head = self.head.load(Ordering::Relaxed);
+        let tail = self.tail.load(Ordering::Relaxed);
+        (tail + 2 * N - head) % (2 * N)
+    }
+
+    /// Returns `true` if the queue contains no elements
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns a pointer to the slot at `index`
+    fn slot(&self, index: usize) -> *mut T {
+        unsafe { (self.buffer.get() as *mut T).add(index % N) }
+    }
+}
+
+impl<T, const N: usize> Default for Queue<T, N> {
+    fn default() -> Self {
+        Queue::new()
+    }
+}
+
+impl<T, const N: usize> fmt::Debug for Queue<T, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Queue")
+            .field("len", &self.len())
+            .field("capacity", &N)
+            .finish()
+    }
+}
+
+/// Splits the queue behind the proxy into its producer and consumer halves
+///
+/// # Panics
+///
+/// This function panics if the capacity of the queue is zero
+pub fn split<S, T, const N: usize>(singleton: S) -> (Producer<S>, Consumer<S>)
+where
+    S: SingletonMut<Type = Queue<T, N>>,
+{
+    assert!(
+        N != 0,
+        "the capacity of the queue must be greater than zero"
+    );
+
+    export::adopt(singleton);
+
+    (
+        Producer {
+            _singleton: PhantomData,
+        },
+        Consumer {
+            _singleton: PhantomData,
+        },
+    )
+}
+
+/// The producer half of the queue behind the singleton `S`
+pub struct Producer<S> {
+    _singleton: PhantomData<S>,
+}
+
+impl<S, T, const N: usize> Producer<S>
+where
+    S: SingletonMut<Type = Queue<T, N>>,
+{
+    /// Adds an element to the back of the queue; returns it back if the queue is full
+    pub fn enqueue(&mut self, item: T) -> Result<(), T> {
+        let queue = queue::<S>();
+        let tail = queue.tail.load(Ordering::Relaxed);
+
+        if (tail + 2 * N - queue.head.load(Ordering::Acquire)) % (2 * N) == N {
+            return Err(item);
+        }
+
+        unsafe { ptr::write(queue.slot(tail), item) }
+        queue.tail.store((tail + 1) % (2 * N), Ordering::Release);
+
+        Ok(())
+    }
+
+    /// Returns `true` if the queue is full
+    pub fn is_full(&self) -> bool {
+        queue::<S>().len() == N
+    }
+
+    /// Returns the number of elements in the queue
+    pub fn len(&self) -> usize {
+        queue::<S>().len()
+    }
+
+    /// Returns `true` if the queue contains no elements
+    pub fn is_empty(&self) -> bool {
+        queue::<S>().is_empty()
+    }
+}
+
+impl<S> fmt::Debug for Producer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Producer")
+    }
+}
+
+/// The consumer half of the queue behind the singleton `S`
+pub struct Consumer<S> {
+    _singleton: PhantomData<S>,
+}
+
+impl<S, T, const N: usize> Consumer<S>
+where
+    S: SingletonMut<Type = Queue<T, N>>,
+{
+    /// Removes the element at the front of the queue, if any
+    pub fn dequeue(&mut self) -> Option<T> {
+        let queue = queue::<S>();
+        let head = queue.head.load(Ordering::Relaxed);
+
+        if head == queue.tail.load(Ordering::Acquire) {
+            return None;
+        }
+
+        let item = unsafe { ptr::read(queue.slot(head)) };
+        queue.head.store((head + 1) % (2 * N), Ordering::Release);
+
+        Some(item)
+    }
+
+    /// Returns a reference to the element at the front of the queue, if any
+    pub fn peek(&self) -> Option<&T> {
+        let queue = queue::<S>();
+        let head = queue.head.load(Ordering::Relaxed);
+
+        if head == queue.tail.load(Ordering::Acquire) {
+            None
+        } else {
+            // NOTE the producer doesn't touch the slot until the consumer moves `head`
+            Some(unsafe { &*queue.slot(head) })
+        }
+    }
+
+    /// Returns the number of elements in the queue
+    pub fn len(&self) -> usize {
+        queue::<S>().len()
+    }
+
+    /// Returns `true` if the queue contains no elements
+    pub fn is_empty(&self) -> bool {
+        queue::<S>().is_empty()
+    }
+}
+
+impl<S> fmt::Debug for Consumer<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Consumer")
+    }
+}
+
+// each half touches its own index and the slots the other half has handed over
+unsafe impl<S, T, const N: usize> Send for Producer<S>
+where
+    S: SingletonMut<Type = Queue<T, N>>,
+    T: Send,
+{
+}
+
+unsafe impl<S, T, const N: usize> Send for Consumer<S>
+where
+    S: SingletonMut<Type = Queue<T, N>>,
+    T: Send,
+{
+}
+
+/// Returns a shared reference to the queue behind the singleton `S`
+///
+/// NOTE all the mutable state of `Queue` is behind `UnsafeCell` or atomics so shared references
+/// are all the halves need
+fn queue<'a, S>() -> &'a S::Type
+where
+    S: SingletonMut,
+{
+    unsafe { &*S::get() }
+}
@@ -0,0 +1,597 @@
+//! Synchronization primitives
+//!
+//! [`Mutex`](struct.Mutex.html) shares a singleton between thread mode and interrupt handlers.
+//! Locking it disables interrupts, using the `critical-section` crate, for as long as the guard is
+//! alive. It requires the `critical-section` feature.
+//!
+//! [`RwLock`](struct.RwLock.html) lets any number of readers access a singleton at the same time,
+//! or a single writer. It doesn't disable interrupts.
+//!
+//! Like their `std` counterparts, the locks are poisoned when a guard that can write to the
+//! variable is dropped during a panic, as the variable may have been left half updated. Locking a
+//! poisoned lock returns a [`PoisonError`](struct.PoisonError.html), which still holds the guard.
+//! Panics are detected when the `std` feature is enabled or, on targets that don't unwind, after
+//! [`enter_panic_handler`](fn.enter_panic_handler.html) is called.
+//!
+//! ```
+//! use owned_singleton::sync::RwLock;
+//! use owned_singleton::Singleton;
+//!
+//! #[Singleton]
+//! static mut LEVELS: [u8; 2] = [0; 2];
+//!
+//! let levels = RwLock::new(unsafe { LEVELS::new() });
+//!
+//! # #[cfg(feature = "std")]
+//! # {
+//! // a panic in the middle of an update
+//! let _ = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+//!     let mut levels = levels.write().unwrap();
+//!     levels[0] = 1;
+//!     panic!();
+//! }));
+//!
+//! assert!(levels.is_poisoned());
+//! let partial = *levels.read().err().unwrap().into_inner();
+//! assert_eq!(partial, [1, 0]);
+//! # }
+//!
+//! // the caller restored a consistent state
+//! *levels.write().unwrap_or_else(|e| e.into_inner()) = [0; 2];
+//! levels.clear_poison();
+//! assert_eq!(*levels.read().unwrap(), [0; 2]);
+//! ```
+
+#[cfg(feature = "critical-section")]
+use core::marker::PhantomData;
+use core::{
+    cell::UnsafeCell,
+    fmt,
+    ops::{Deref, DerefMut},
+};
+
+#[cfg(feature = "critical-section")]
+use critical_section::{self, RestoreState};
+
+use atomic::{AtomicBool, AtomicUsize, Ordering};
+use SingletonMut;
+
+/// Set by `enter_panic_handler`
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
+/// Makes the guards dropped from now on poison their locks
+///
+/// Call this at the start of the `#[panic_handler]` of targets that don't unwind. Guards held by
+/// the code that panicked are never dropped there, so those locks stay locked instead, but
+/// interrupt handlers that keep running while the panic handler waits, e.g. for a reset, poison
+/// the locks whose guards they drop.
+pub fn enter_panic_handler() {
+    PANICKING.store(true, Ordering::Relaxed)
+}
+
+/// Whether a panic is in progress
+fn panicking() -> bool {
+    #[cfg(feature = "std")]
+    {
+        if std::thread::panicking() {
+            return true;
+        }
+    }
+
+    PANICKING.load(Ordering::Relaxed)
+}
+
+/// A lock that has been poisoned
+///
+/// It holds the guard; the variable can still be accessed through it.
+pub struct PoisonError<G> {
+    guard: G,
+}
+
+impl<G> PoisonError<G> {
+    /// Returns the guard
+    pub fn into_inner(self) -> G {
+        self.guard
+    }
+
+    /// Returns a reference to the guard
+    pub fn get_ref(&self) -> &G {
+        &self.guard
+    }
+
+    /// Returns a mutable reference to the guard
+    pub fn get_mut(&mut self) -> &mut G {
+        &mut self.guard
+    }
+}
+
+impl<G> fmt::Debug for PoisonError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PoisonError").finish()
+    }
+}
+
+impl<G> fmt::Display for PoisonError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("the lock was poisoned by a panic")
+    }
+}
+
+/// The error of the `try_*` lock methods
+pub enum TryLockError<G> {
+    /// The lock is poisoned; the lock has been acquired anyway
+    Poisoned(PoisonError<G>),
+    /// The lock is already held
+    WouldBlock,
+}
+
+impl<G> From<PoisonError<G>> for TryLockError<G> {
+    fn from(e: PoisonError<G>) -> Self {
+        TryLockError::Poisoned(e)
+    }
+}
+
+impl<G> fmt::Debug for TryLockError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TryLockError::Poisoned(e) => f.debug_tuple("Poisoned").field(e).finish(),
+            TryLockError::WouldBlock => f.write_str("WouldBlock"),
+        }
+    }
+}
+
+impl<G> fmt::Display for TryLockError<G> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TryLockError::Poisoned(e) => fmt::Display::fmt(e, f),
+            TryLockError::WouldBlock => f.write_str("the lock is already held"),
+        }
+    }
+}
+
+/// The result of the blocking lock methods
+pub type LockResult<G> = Result<G, PoisonError<G>>;
+
+/// The result of the `try_*` lock methods
+pub type TryLockResult<G> = Result<G, TryLockError<G>>;
+
+/// Wraps `guard` in a `PoisonError` if the lock is poisoned
+fn check<G>(poisoned: &AtomicBool, guard: G) -> LockResult<G> {
+    if poisoned.load(Ordering::Relaxed) {
+        Err(PoisonError { guard })
+    } else {
+        Ok(guard)
+    }
+}
+
+/// Poisons the lock if a guard is being dropped during a panic
+fn poison_if_panicking(poisoned: &AtomicBool) {
+    if panicking() {
+        poisoned.store(true, Ordering::Relaxed)
+    }
+}
+
+#[cfg(feature = "critical-section")]
+/// A mutual exclusion lock around the singleton `S`
+pub struct Mutex<S> {
+    singleton: UnsafeCell<S>,
+    locked: AtomicBool,
+    poisoned: AtomicBool,
+}
+
+#[cfg(feature = "critical-section")]
+impl<S> Mutex<S>
+where
+    S: SingletonMut,
+{
+    /// Wraps the proxy
+    pub fn new(singleton: S) -> Self {
+        Mutex {
+            singleton: UnsafeCell::new(singleton),
+            locked: AtomicBool::new(false),
+            poisoned: AtomicBool::new(false),
+        }
+    }
+
+    /// Locks the mutex
+    ///
+    /// Returns an error, which holds the guard, if the mutex is poisoned.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the mutex is already locked. On a single core system the lock can't
+    /// be released while interrupts are disabled, so waiting for it would never return.
+    pub fn lock<'a>(&'a self) -> LockResult<MutexGuard<'a, S>> {
+        match self.try_lock() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(e)) => Err(e),
+            Err(TryLockError::WouldBlock) => panic!("the `Mutex` is already locked"),
+        }
+    }
+
+    /// Locks the mutex, unless it's already locked
+    pub fn try_lock<'a>(&'a self) -> TryLockResult<MutexGuard<'a, S>> {
+        let restore = unsafe { critical_section::acquire() };
+
+        // NOTE interrupts are disabled so load + store can't be preempted
+        if self.locked.load(Ordering::Relaxed) {
+            unsafe { critical_section::release(restore) }
+            Err(TryLockError::WouldBlock)
+        } else {
+            self.locked.store(true, Ordering::Relaxed);
+
+            let guard = MutexGuard {
+                mutex: self,
+                restore,
+                _not_send_or_sync: PhantomData,
+            };
+
+            Ok(check(&self.poisoned, guard)?)
+        }
+    }
+
+    /// Locks the mutex and calls `f` with a mutable reference to the variable behind the proxy
+    ///
+    /// This ignores poisoning; use `lock` to find out whether the mutex is poisoned.
+    pub fn with_lock<R, F>(&self, f: F) -> R
+    where
+        F: FnOnce(&mut S::Type) -> R,
+    {
+        f(&mut self.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+
+    /// Returns `true` if the mutex is poisoned
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+    }
+
+    /// Marks the mutex as not poisoned, e.g. after the variable has been restored to a consistent
+    /// state
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Relaxed)
+    }
+
+    /// Returns a mutable reference to the variable behind the proxy
+    ///
+    /// No locking is needed as the mutable borrow guarantees exclusive access.
+    pub fn get_mut(&mut self) -> &mut S::Type {
+        self.singleton.get_mut()
+    }
+
+    /// Releases the proxy
+    pub fn into_inner(self) -> S {
+        self.singleton.into_inner()
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<S> fmt::Debug for Mutex<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Mutex")
+            .field("locked", &self.locked.load(Ordering::Relaxed))
+            .field("poisoned", &self.poisoned.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+#[cfg(feature = "critical-section")]
+// the lock serializes all accesses to the variable
+unsafe impl<S> Sync for Mutex<S> where S: Send {}
+
+#[cfg(feature = "critical-section")]
+/// A locked `Mutex`
+///
+/// Interrupts stay disabled until the guard is dropped. Guards must be dropped in the reverse
+/// order in which they were created; otherwise interrupts may be re-enabled too early. That's not
+/// unsound, as the mutex stays locked, but interrupt handlers that try to lock it will panic.
+pub struct MutexGuard<'a, S>
+where
+    S: 'a,
+{
+    mutex: &'a Mutex<S>,
+    restore: RestoreState,
+    _not_send_or_sync: PhantomData<*const ()>,
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, S> Deref for MutexGuard<'a, S>
+where
+    S: SingletonMut,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*self.mutex.singleton.get() }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, S> DerefMut for MutexGuard<'a, S>
+where
+    S: SingletonMut,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut S::Type {
+        // NOTE the guard is the only way to reach the variable while the mutex is locked
+        unsafe { &mut *self.mutex.singleton.get() }
+    }
+}
+
+#[cfg(feature = "critical-section")]
+impl<'a, S> Drop for MutexGuard<'a, S> {
+    fn drop(&mut self) {
+        poison_if_panicking(&self.mutex.poisoned);
+        self.mutex.locked.store(false, Ordering::Release);
+        unsafe { critical_section::release(self.restore) }
+    }
+}
+
+/// The value of `RwLock.state` while the lock is held by a writer; any other value is the number
+/// of readers
+const WRITER: usize = !0;
+
+/// A reader-writer lock around the singleton `S`
+pub struct RwLock<S> {
+    singleton: UnsafeCell<S>,
+    state: AtomicUsize,
+    poisoned: AtomicBool,
+}
+
+impl<S> RwLock<S>
+where
+    S: SingletonMut,
+{
+    /// Wraps the proxy
+    pub fn new(singleton: S) -> Self {
+        RwLock {
+            singleton: UnsafeCell::new(singleton),
+            state: AtomicUsize::new(0),
+            poisoned: AtomicBool::new(false),
+        }
+    }
+
+    /// Locks the lock for reading
+    ///
+    /// Returns an error, which holds the guard, if the lock is poisoned.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the lock is held by a writer. On a single core system the writer
+    /// can't release the lock while it's preempted, so waiting for it would never return.
+    pub fn read<'a>(&'a self) -> LockResult<RwLockReadGuard<'a, S>> {
+        match self.try_read() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(e)) => Err(e),
+            Err(TryLockError::WouldBlock) => panic!("the `RwLock` is already locked for writing"),
+        }
+    }
+
+    /// Locks the lock for reading, unless it's held by a writer
+    pub fn try_read<'a>(&'a self) -> TryLockResult<RwLockReadGuard<'a, S>> {
+        if self.lock_shared() {
+            Ok(check(&self.poisoned, RwLockReadGuard { lock: self })?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+
+    /// Locks the lock for writing
+    ///
+    /// Returns an error, which holds the guard, if the lock is poisoned.
+    ///
+    /// # Panics
+    ///
+    /// This method panics if the lock is held by a reader or a writer.
+    pub fn write<'a>(&'a self) -> LockResult<RwLockWriteGuard<'a, S>> {
+        match self.try_write() {
+            Ok(guard) => Ok(guard),
+            Err(TryLockError::Poisoned(e)) => Err(e),
+            Err(TryLockError::WouldBlock) => panic!("the `RwLock` is already locked"),
+        }
+    }
+
+    /// Locks the lock for writing, unless it's held by a reader or a writer
+    pub fn try_write<'a>(&'a self) -> TryLockResult<RwLockWriteGuard<'a, S>> {
+        if self.lock_exclusive() {
+            Ok(check(&self.poisoned, RwLockWriteGuard { lock: self })?)
+        } else {
+            Err(TryLockError::WouldBlock)
+        }
+    }
+
+    /// Returns `true` if the lock is poisoned
+    pub fn is_poisoned(&self) -> bool {
+        self.poisoned.load(Ordering::Relaxed)
+    }
+
+    /// Marks the lock as not poisoned, e.g. after the variable has been restored to a consistent
+    /// state
+    pub fn clear_poison(&self) {
+        self.poisoned.store(false, Ordering::Relaxed)
+    }
+
+    /// Returns a mutable reference to the variable behind the proxy
+    ///
+    /// No locking is needed as the mutable borrow guarantees exclusive access.
+    pub fn get_mut(&mut self) -> &mut S::Type {
+        self.singleton.get_mut()
+    }
+
+    /// Releases the proxy
+    pub fn into_inner(self) -> S {
+        self.singleton.into_inner()
+    }
+}
+
+impl<S> RwLock<S> {
+    /// Adds one reader; returns `false` if the lock is held by a writer
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    fn lock_shared(&self) -> bool {
+        let mut state = self.state.load(Ordering::Relaxed);
+        loop {
+            // NOTE `WRITER - 1` readers would make the count indistinguishable from `WRITER`
+            if state >= WRITER - 1 {
+                return false;
+            }
+
+            match self.state.compare_exchange_weak(
+                state,
+                state + 1,
+                Ordering::Acquire,
+                Ordering::Relaxed,
+            ) {
+                Ok(_) => return true,
+                Err(current) => state = current,
+            }
+        }
+    }
+
+    /// Adds one reader; returns `false` if the lock is held by a writer
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    fn lock_shared(&self) -> bool {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            let state = self.state.load(Ordering::Relaxed);
+            if state >= WRITER - 1 {
+                false
+            } else {
+                self.state.store(state + 1, Ordering::Relaxed);
+                true
+            }
+        })
+    }
+
+    /// Removes one reader
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    fn unlock_shared(&self) {
+        self.state.fetch_sub(1, Ordering::Release);
+    }
+
+    /// Removes one reader
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    fn unlock_shared(&self) {
+        critical_section::with(|_| {
+            let state = self.state.load(Ordering::Relaxed);
+            self.state.store(state - 1, Ordering::Relaxed);
+        })
+    }
+
+    /// Takes the writer lock; returns `false` if the lock is held by a reader or a writer
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    fn lock_exclusive(&self) -> bool {
+        self.state
+            .compare_exchange(0, WRITER, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+    }
+
+    /// Takes the writer lock; returns `false` if the lock is held by a reader or a writer
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    fn lock_exclusive(&self) -> bool {
+        critical_section::with(|_| {
+            if self.state.load(Ordering::Relaxed) == 0 {
+                self.state.store(WRITER, Ordering::Relaxed);
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+impl<S> fmt::Debug for RwLock<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = self.state.load(Ordering::Relaxed);
+        f.debug_struct("RwLock")
+            .field("readers", &if state == WRITER { 0 } else { state })
+            .field("writer", &(state == WRITER))
+            .field("poisoned", &self.poisoned.load(Ordering::Relaxed))
+            .finish()
+    }
+}
+
+// readers share `&S::Type` across threads; writers hand out `&mut S::Type`
+unsafe impl<S> Sync for RwLock<S> where S: Send + Sync {}
+
+/// A `RwLock` locked for reading
+pub struct RwLockReadGuard<'a, S>
+where
+    S: 'a,
+{
+    lock: &'a RwLock<S>,
+}
+
+impl<'a, S> Deref for RwLockReadGuard<'a, S>
+where
+    S: SingletonMut,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*self.lock.singleton.get() }
+    }
+}
+
+impl<'a, S> Drop for RwLockReadGuard<'a, S> {
+    fn drop(&mut self) {
+        self.lock.unlock_shared();
+    }
+}
+
+/// A `RwLock` locked for writing
+pub struct RwLockWriteGuard<'a, S>
+where
+    S: 'a,
+{
+    lock: &'a RwLock<S>,
+}
+
+impl<'a, S> Deref for RwLockWriteGuard<'a, S>
+where
+    S: SingletonMut,
+{
+    type Target = S::Type;
+
+    #[inline]
+    fn deref(&self) -> &S::Type {
+        unsafe { &*self.lock.singleton.get() }
+    }
+}
+
+impl<'a, S> DerefMut for RwLockWriteGuard<'a, S>
+where
+    S: SingletonMut,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut S::Type {
+        // NOTE the guard is the only way to reach the variable while the lock is held by a writer
+        unsafe { &mut *self.lock.singleton.get() }
+    }
+}
+
+impl<'a, S> Drop for RwLockWriteGuard<'a, S> {
+    fn drop(&mut self) {
+        // NOTE only writers can leave the variable half updated
+        poison_if_panicking(&self.lock.poisoned);
+        self.lock.state.store(0, Ordering::Release);
+    }
+}
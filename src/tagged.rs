@@ -0,0 +1,230 @@
+//! Singletons whose uniqueness is tied to a type
+//!
+//! A [`SingletonCell<T, G>`](struct.SingletonCell.html) is plain `static` storage for a `T`; the
+//! zero-sized tag type `G` names it. [`Handle<G>`](struct.Handle.html) is the proxy of the cell
+//! and implements `SingletonMut<Type = T>`. As cells, tags and handles are ordinary generic types
+//! library crates can declare singleton storage, or accept it from the application, without the
+//! `Singleton` attribute.
+//!
+//! ```
+//! use owned_singleton::tagged::{Handle, SingletonCell, Tag};
+//!
+//! /// Names the receive buffer
+//! pub enum RxBuf {}
+//!
+//! static RX_BUF: SingletonCell<[u8; 64], RxBuf> = SingletonCell::new([0; 64]);
+//!
+//! unsafe impl Tag for RxBuf {
+//!     type Type = [u8; 64];
+//!
+//!     const NAME: &'static str = "RxBuf";
+//!
+//!     fn cell() -> &'static SingletonCell<[u8; 64], RxBuf> {
+//!         &RX_BUF
+//!     }
+//! }
+//!
+//! // a driver, in some library, generic over the tag of its buffer
+//! pub struct Serial<G>
+//! where
+//!     G: Tag<Type = [u8; 64]>,
+//! {
+//!     buf: Handle<G>,
+//! }
+//!
+//! impl<G> Serial<G>
+//! where
+//!     G: Tag<Type = [u8; 64]>,
+//! {
+//!     pub fn receive(&mut self, byte: u8) {
+//!         self.buf[0] = byte;
+//!     }
+//! }
+//!
+//! let mut serial = Serial { buf: Handle::<RxBuf>::take().unwrap() };
+//! serial.receive(1);
+//!
+//! assert!(Handle::<RxBuf>::take().is_none());
+//! assert_eq!(serial.buf[0], 1);
+//! ```
+
+use core::cell::UnsafeCell;
+use core::fmt;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+
+use stable_deref_trait::StableDeref;
+
+use atomic::{AtomicU8, Ordering};
+use {Singleton, SingletonMut};
+
+/// The handle of the cell hasn't been taken
+const FREE: u8 = 0;
+/// The handle of the cell has been taken
+const TAKEN: u8 = 1;
+
+/// A zero-sized type that names a `SingletonCell`
+///
+/// # Safety
+///
+/// `cell` must always return a reference to the same `static` cell
+pub unsafe trait Tag: Sized + 'static {
+    /// The type of the value stored in the cell
+    type Type: 'static;
+
+    /// The name of the handle; see `Singleton::NAME`
+    const NAME: &'static str = "Handle";
+
+    /// Returns the cell this type names
+    fn cell() -> &'static SingletonCell<Self::Type, Self>;
+}
+
+/// Storage for a singleton whose uniqueness unit is the tag type `G`
+pub struct SingletonCell<T, G> {
+    value: UnsafeCell<T>,
+    state: AtomicU8,
+    _tag: PhantomData<fn() -> G>,
+}
+
+impl<T, G> SingletonCell<T, G> {
+    /// Creates a cell that holds `value`
+    pub const fn new(value: T) -> Self {
+        SingletonCell {
+            value: UnsafeCell::new(value),
+            state: AtomicU8::new(FREE),
+            _tag: PhantomData,
+        }
+    }
+
+    /// Marks the handle of the cell as taken; returns `false` if it already was
+    #[cfg(all(
+        not(feature = "cs-take"),
+        any(target_has_atomic = "8", feature = "portable-atomic")
+    ))]
+    #[inline]
+    fn acquire(&self) -> bool {
+        self.state.swap(TAKEN, Ordering::Acquire) == FREE
+    }
+
+    /// Marks the handle of the cell as taken; returns `false` if it already was
+    #[cfg(any(
+        feature = "cs-take",
+        all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+    ))]
+    #[inline]
+    fn acquire(&self) -> bool {
+        // NOTE only atomic loads and stores are used so this works on targets without CAS
+        critical_section::with(|_| {
+            if self.state.load(Ordering::Relaxed) == FREE {
+                self.state.store(TAKEN, Ordering::Relaxed);
+                true
+            } else {
+                false
+            }
+        })
+    }
+}
+
+// NOTE the value is only reachable through the handle
+unsafe impl<T, G> Sync for SingletonCell<T, G> where T: Send {}
+
+/// The proxy of the `SingletonCell` named by the tag `G`
+pub struct Handle<G> {
+    _marker: PhantomData<(G, *const ())>,
+}
+
+impl<G> Handle<G>
+where
+    G: Tag,
+{
+    /// Takes the handle of the cell
+    ///
+    /// Returns `None` if the handle has already been taken
+    pub fn take() -> Option<Self> {
+        if G::cell().acquire() {
+            Some(Handle {
+                _marker: PhantomData,
+            })
+        } else {
+            None
+        }
+    }
+
+    // used by `resources!` to undo a partial take
+    #[doc(hidden)]
+    #[inline]
+    pub fn __give_back(self) {
+        G::cell().state.store(FREE, Ordering::Release)
+    }
+}
+
+unsafe impl<G> Singleton for Handle<G>
+where
+    G: Tag,
+{
+    type Type = G::Type;
+
+    const NAME: &'static str = G::NAME;
+
+    #[inline]
+    unsafe fn new() -> Self {
+        Handle {
+            _marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn get() -> *mut G::Type {
+        G::cell().value.get()
+    }
+}
+
+unsafe impl<G> SingletonMut for Handle<G> where G: Tag {}
+
+impl<G> Deref for Handle<G>
+where
+    G: Tag,
+{
+    type Target = G::Type;
+
+    #[inline]
+    fn deref(&self) -> &G::Type {
+        unsafe { &*Self::get() }
+    }
+}
+
+impl<G> DerefMut for Handle<G>
+where
+    G: Tag,
+{
+    #[inline]
+    fn deref_mut(&mut self) -> &mut G::Type {
+        unsafe { &mut *Self::get() }
+    }
+}
+
+unsafe impl<G> StableDeref for Handle<G> where G: Tag {}
+
+unsafe impl<G> Send for Handle<G>
+where
+    G: Tag,
+    G::Type: Send,
+{
+}
+
+unsafe impl<G> Sync for Handle<G>
+where
+    G: Tag,
+    G::Type: Sync,
+{
+}
+
+impl<G> fmt::Debug for Handle<G>
+where
+    G: Tag,
+    G::Type: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple(G::NAME).field(&**self).finish()
+    }
+}
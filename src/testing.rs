@@ -0,0 +1,245 @@
+//! Mock singletons and test isolation for host-side tests
+//!
+//! [`MockSingleton<T>`](struct.MockSingleton.html) implements `SingletonMut<Type = T>` without a
+//! `static` variable behind it, so generic code written against `S: SingletonMut<Type = T>` can be
+//! unit tested on the host.
+//!
+//! Tests that use real singletons share their `static` variables, and `take` flags, with every
+//! other test in the same process. [`reset_all`](fn.reset_all.html) puts them back in their
+//! initial state: variables declared with the `reset` argument get their initial value back and
+//! `take` can be called again.
+//! `reset_all` is only available on targets whose linker is an ELF linker (e.g. Linux).
+//!
+//! ```no_run
+//! # #[cfg(target_os = "linux")]
+//! # fn main() {
+//! use owned_singleton::{testing, Singleton};
+//!
+//! #[Singleton(reset)]
+//! static mut COUNT: u32 = 0;
+//!
+//! // #[test]
+//! fn counts() {
+//!     unsafe { testing::reset_all() }
+//!
+//!     let mut count = unsafe { COUNT::new() };
+//!     *count += 1;
+//!     assert_eq!(*count, 1);
+//! }
+//! # counts()
+//! # }
+//! # #[cfg(not(target_os = "linux"))]
+//! # fn main() {}
+//! ```
+
+use core::any::{Any, TypeId};
+use core::fmt;
+use core::marker::PhantomData;
+use core::mem;
+use core::ops::{Deref, DerefMut};
+
+use std::boxed::Box;
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use stable_deref_trait::StableDeref;
+
+use {Singleton, SingletonMut};
+
+std::thread_local! {
+    /// The values of the mocks that are alive on this thread, by type
+    static VALUES: RefCell<HashMap<TypeId, Box<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// A singleton whose variable lives on the heap of the current thread
+///
+/// There can be at most one `MockSingleton<T>` per type `T` on each thread, mirroring the one
+/// proxy per `static` variable of real singletons; tests that run in parallel get a separate value
+/// each. The value is dropped when the mock is.
+pub struct MockSingleton<T>
+where
+    T: 'static,
+{
+    // NOTE the value belongs to the current thread
+    _marker: PhantomData<*mut T>,
+}
+
+impl<T> MockSingleton<T>
+where
+    T: 'static,
+{
+    /// Creates a mock whose variable holds `value`
+    ///
+    /// # Panics
+    ///
+    /// This function panics if a `MockSingleton<T>` is already alive on this thread
+    pub fn new(value: T) -> Self {
+        VALUES.with(|values| {
+            let mut values = values.borrow_mut();
+            if values.contains_key(&TypeId::of::<T>()) {
+                panic!(
+                    "a `MockSingleton<{}>` is already alive on this thread",
+                    core::any::type_name::<T>()
+                );
+            }
+
+            values.insert(TypeId::of::<T>(), Box::new(value));
+        });
+
+        MockSingleton {
+            _marker: PhantomData,
+        }
+    }
+
+    /// Destroys the mock and returns the value of its variable
+    pub fn into_inner(self) -> T {
+        let value = Self::remove();
+        // the value has already been removed
+        mem::forget(self);
+        value
+    }
+
+    fn remove() -> T {
+        VALUES.with(|values| {
+            let value = values.borrow_mut().remove(&TypeId::of::<T>()).unwrap();
+            *value.downcast().unwrap()
+        })
+    }
+}
+
+unsafe impl<T> Singleton for MockSingleton<T>
+where
+    T: 'static,
+{
+    type Type = T;
+
+    const NAME: &'static str = "MockSingleton";
+
+    /// Creates another handle to the mock that's alive on this thread
+    ///
+    /// # Panics
+    ///
+    /// This function panics if no `MockSingleton<T>` is alive on this thread
+    unsafe fn new() -> Self {
+        // make sure there's a value
+        let _ = Self::get();
+
+        MockSingleton {
+            _marker: PhantomData,
+        }
+    }
+
+    /// # Panics
+    ///
+    /// This function panics if no `MockSingleton<T>` is alive on this thread
+    fn get() -> *mut T {
+        VALUES.with(|values| {
+            // NOTE the value is boxed so its address doesn't change when the map grows
+            match values.borrow_mut().get_mut(&TypeId::of::<T>()) {
+                Some(value) => value.downcast_mut::<T>().unwrap() as *mut T,
+                None => panic!(
+                    "no `MockSingleton<{}>` is alive on this thread",
+                    core::any::type_name::<T>()
+                ),
+            }
+        })
+    }
+}
+
+unsafe impl<T> SingletonMut for MockSingleton<T> where T: 'static {}
+
+impl<T> Deref for MockSingleton<T>
+where
+    T: 'static,
+{
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*Self::get() }
+    }
+}
+
+impl<T> DerefMut for MockSingleton<T>
+where
+    T: 'static,
+{
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *Self::get() }
+    }
+}
+
+unsafe impl<T> StableDeref for MockSingleton<T> where T: 'static {}
+
+impl<T> Drop for MockSingleton<T>
+where
+    T: 'static,
+{
+    fn drop(&mut self) {
+        drop(Self::remove())
+    }
+}
+
+impl<T> fmt::Debug for MockSingleton<T>
+where
+    T: fmt::Debug + 'static,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("MockSingleton").field(&**self).finish()
+    }
+}
+
+/// Restores the initial state of all the singletons declared in test builds
+///
+/// Variables declared with the `reset` argument get their initial value back and all the `take`
+/// flags are released. `init` variables can be initialized again; their current value is leaked.
+/// Per-CPU variables and variables declared without `reset` keep their contents. Only the
+/// singletons of crates compiled with `cfg(test)` are reset, e.g. the crate under test when
+/// running its unit tests.
+///
+/// This walks the `owned_singleton_reset` linker section, which relies on the `__start_` /
+/// `__stop_` symbols that ELF linkers define for it, so this function is only available on
+/// Android, FreeBSD, Fuchsia, illumos, Linux and OpenBSD.
+///
+/// # Safety
+///
+/// No proxy, or reference to the variable behind one, may be in use. As the test harness runs
+/// tests in parallel threads by default, tests that call this must be serialized, e.g. with
+/// `--test-threads=1`.
+#[cfg(any(
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "illumos",
+    target_os = "linux",
+    target_os = "openbsd"
+))]
+pub unsafe fn reset_all() {
+    use core::slice;
+
+    extern "C" {
+        static __start_owned_singleton_reset: u8;
+        static __stop_owned_singleton_reset: u8;
+    }
+
+    let start = &__start_owned_singleton_reset as *const u8 as *const unsafe fn();
+    let stop = &__stop_owned_singleton_reset as *const u8 as *const unsafe fn();
+    let len = (stop as usize - start as usize) / mem::size_of::<unsafe fn()>();
+
+    for reset in slice::from_raw_parts(start, len) {
+        reset()
+    }
+}
+
+// makes sure the section exists, and thus its start and stop symbols, even if the program has no
+// singletons
+#[cfg(any(
+    target_os = "android",
+    target_os = "freebsd",
+    target_os = "fuchsia",
+    target_os = "illumos",
+    target_os = "linux",
+    target_os = "openbsd"
+))]
+#[link_section = "owned_singleton_reset"]
+#[used]
+static EMPTY: [unsafe fn(); 0] = [];
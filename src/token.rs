@@ -0,0 +1,44 @@
+//! Proof of creation tokens
+
+use core::{fmt, marker::PhantomData};
+
+use {NotPerCpu, Singleton};
+
+/// Zero sized proof that the singleton `S` has been created
+///
+/// A token doesn't grant access to the variable behind `S`; it can be freely copied and used as
+/// evidence that some initialization step, done while creating the singleton, has taken place.
+pub struct Token<S> {
+    _singleton: PhantomData<fn() -> S>,
+}
+
+impl<S> Token<S>
+where
+    S: Singleton,
+{
+    /// Creates a token from a reference to the singleton
+    #[inline]
+    pub fn new(_singleton: &S) -> Self {
+        #[allow(clippy::let_unit_value)]
+        let () = NotPerCpu::<S>::CHECK;
+
+        Token {
+            _singleton: PhantomData,
+        }
+    }
+}
+
+impl<S> Clone for Token<S> {
+    #[inline]
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<S> Copy for Token<S> {}
+
+impl<S> fmt::Debug for Token<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Token")
+    }
+}
@@ -0,0 +1,116 @@
+//! Access instrumentation
+//!
+//! With the `trace` feature every proxy that implements `Deref` counts how many times the
+//! variable behind it is read, through `Deref`, and written, through `DerefMut`. The generated
+//! `counters` function returns the [`Counters`](struct.Counters.html) of a singleton. A
+//! [hook](fn.set_hook.html) can also be invoked on each access, e.g. to check which context is
+//! accessing the singleton or to forward the accesses to a tracing tool.
+
+use core::mem;
+
+use atomic::{AtomicUsize, Ordering};
+
+/// The kind of access to the variable behind a proxy
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Access {
+    /// The variable was dereferenced through `Deref`
+    Read,
+    /// The variable was dereferenced through `DerefMut`
+    Write,
+}
+
+/// A function that's called on every access; it receives the name of the singleton
+pub type Hook = fn(name: &'static str, access: Access);
+
+/// The address of the hook; 0 if no hook has been set
+static HOOK: AtomicUsize = AtomicUsize::new(0);
+
+/// Sets the function that's called on every access to a singleton
+///
+/// The hook runs in the context that accesses the singleton, e.g. an interrupt handler, so it
+/// should be short. Accesses made by the hook itself invoke it again.
+pub fn set_hook(hook: Hook) {
+    HOOK.store(hook as usize, Ordering::Release)
+}
+
+/// Removes the hook
+pub fn clear_hook() {
+    HOOK.store(0, Ordering::Release)
+}
+
+/// The number of accesses to the variable behind a proxy
+pub struct Counters {
+    reads: AtomicUsize,
+    writes: AtomicUsize,
+}
+
+impl Counters {
+    /// Creates counters that start at zero
+    pub const fn new() -> Self {
+        Counters {
+            reads: AtomicUsize::new(0),
+            writes: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the number of reads
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of writes
+    pub fn writes(&self) -> usize {
+        self.writes.load(Ordering::Relaxed)
+    }
+
+    /// Sets both counters back to zero
+    pub fn reset(&self) {
+        self.reads.store(0, Ordering::Relaxed);
+        self.writes.store(0, Ordering::Relaxed);
+    }
+
+    /// Counts an access and calls the hook, if any
+    #[doc(hidden)]
+    #[inline]
+    pub fn record(&self, name: &'static str, access: Access) {
+        increment(match access {
+            Access::Read => &self.reads,
+            Access::Write => &self.writes,
+        });
+
+        let hook = HOOK.load(Ordering::Acquire);
+        if hook != 0 {
+            // NOTE only `set_hook` writes non-zero values and those are `Hook`s
+            let hook: Hook = unsafe { mem::transmute(hook) };
+            hook(name, access)
+        }
+    }
+}
+
+impl Default for Counters {
+    fn default() -> Self {
+        Counters::new()
+    }
+}
+
+#[cfg(all(
+    not(feature = "cs-take"),
+    any(target_has_atomic = "8", feature = "portable-atomic")
+))]
+#[inline]
+fn increment(counter: &AtomicUsize) {
+    counter.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(any(
+    feature = "cs-take",
+    all(not(target_has_atomic = "8"), not(feature = "portable-atomic"))
+))]
+#[inline]
+fn increment(counter: &AtomicUsize) {
+    // NOTE only atomic loads and stores are used so this works on targets without CAS
+    critical_section::with(|_| {
+        let count = counter.load(Ordering::Relaxed);
+        counter.store(count.wrapping_add(1), Ordering::Relaxed)
+    })
+}
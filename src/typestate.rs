@@ -0,0 +1,11 @@
+//! States of the proxies of `typestate` singletons
+//!
+//! The `typestate` argument makes the proxy generic over its state: `FOO<Uninit>` can only
+//! initialize the variable, which turns it into a `FOO<Init>`, or simply `FOO`, that dereferences
+//! to the value.
+
+/// The variable behind the proxy has not been initialized
+pub enum Uninit {}
+
+/// The variable behind the proxy has been initialized
+pub enum Init {}
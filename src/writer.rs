@@ -0,0 +1,77 @@
+//! Formatting into byte buffers
+
+use core::{fmt, str};
+
+use SingletonMut;
+
+/// A `fmt::Write` sink backed by a byte buffer singleton
+///
+/// Text is appended to the buffer behind `S`; once the buffer is full `write_str` writes as much
+/// as fits and returns an error. The variable behind `S` must be a `[u8; N]` array.
+pub struct Writer<S> {
+    singleton: S,
+    cursor: usize,
+}
+
+impl<S, const N: usize> Writer<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    /// Wraps the proxy; the buffer starts empty
+    pub fn new(singleton: S) -> Self {
+        Writer {
+            singleton,
+            cursor: 0,
+        }
+    }
+
+    /// Returns the text written so far
+    pub fn as_str(&self) -> &str {
+        // NOTE `write_str` only writes whole UTF-8 sequences
+        unsafe { str::from_utf8_unchecked(&(*self.singleton)[..self.cursor]) }
+    }
+
+    /// Discards the text written so far
+    pub fn clear(&mut self) {
+        self.cursor = 0;
+    }
+
+    /// Returns the number of bytes written so far
+    pub fn len(&self) -> usize {
+        self.cursor
+    }
+
+    /// Returns `true` if nothing has been written yet
+    pub fn is_empty(&self) -> bool {
+        self.cursor == 0
+    }
+
+    /// Releases the proxy
+    pub fn into_inner(self) -> S {
+        self.singleton
+    }
+}
+
+impl<S, const N: usize> fmt::Write for Writer<S>
+where
+    S: SingletonMut<Type = [u8; N]>,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let buffer = &mut (*self.singleton)[self.cursor..];
+
+        // truncate at a character boundary
+        let mut n = s.len().min(buffer.len());
+        while !s.is_char_boundary(n) {
+            n -= 1;
+        }
+
+        buffer[..n].copy_from_slice(&s.as_bytes()[..n]);
+        self.cursor += n;
+
+        if n == s.len() {
+            Ok(())
+        } else {
+            Err(fmt::Error)
+        }
+    }
+}
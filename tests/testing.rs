@@ -0,0 +1,44 @@
+//! `testing::reset_all` restores the singletons declared in test builds
+
+#![cfg(all(
+    feature = "std",
+    any(
+        target_os = "android",
+        target_os = "freebsd",
+        target_os = "fuchsia",
+        target_os = "illumos",
+        target_os = "linux",
+        target_os = "openbsd"
+    )
+))]
+
+extern crate owned_singleton;
+
+use owned_singleton::{testing, Singleton};
+
+#[Singleton(reset)]
+static mut COUNT: u32 = 0;
+
+#[Singleton(reset)]
+static mut LOG: [u8; 4] = [0; 4];
+
+// NOTE a single test because `reset_all` must not run concurrently with other tests
+#[test]
+fn reset_all() {
+    *unsafe { COUNT::new() } += 1;
+    (*unsafe { LOG::new() })[0] = 1;
+
+    #[cfg(feature = "take")]
+    {
+        assert!(COUNT::take().is_some());
+        assert!(COUNT::take().is_none());
+    }
+
+    unsafe { testing::reset_all() }
+
+    assert_eq!(*unsafe { COUNT::new() }, 0);
+    assert_eq!(*unsafe { LOG::new() }, [0; 4]);
+
+    #[cfg(feature = "take")]
+    assert!(COUNT::take().is_some());
+}